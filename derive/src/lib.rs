@@ -0,0 +1,128 @@
+#![warn(unused, future_incompatible, nonstandard_style, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+//! Derive macros for the absorption traits of `ark-sponge`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Ident, Type};
+
+/// Derives `ark_sponge::constraints::AbsorbGadget<CF>` for a struct or an enum.
+///
+/// A struct absorbs each of its fields in declaration order. An enum first absorbs the index of
+/// the variant (starting from 0, regardless of explicit discriminants) as a constant, then the
+/// fields of that variant in declaration order. Bytes encode the index as a little-endian
+/// `u32`, and field elements encode it as a single element.
+///
+/// The impl is generic over the constraint field and requires every field type to implement
+/// `AbsorbGadget` for it.
+#[proc_macro_derive(AbsorbGadget)]
+pub fn derive_absorb_gadget(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    proc_macro::TokenStream::from(impl_absorb_gadget(&ast))
+}
+
+/// A pattern destructuring `fields`, and the names it binds.
+fn destructure(fields: &Fields) -> (TokenStream, Vec<Ident>) {
+    let bindings: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__field_{}", i))
+        .collect();
+    let pattern = match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|field| &field.ident);
+            quote! { { #(#names: #bindings),* } }
+        }
+        Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+        Fields::Unit => quote! {},
+    };
+    (pattern, bindings)
+}
+
+fn impl_absorb_gadget(ast: &DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+    let private = quote! { ::ark_sponge::constraints::__derive };
+
+    // Each arm absorbs one shape of `Self`: the only shape of a struct, or one variant of an enum.
+    let mut field_types = Vec::<&Type>::new();
+    let mut bytes_arms = Vec::<TokenStream>::new();
+    let mut elements_arms = Vec::<TokenStream>::new();
+    match &ast.data {
+        Data::Struct(data) => {
+            let (pattern, bindings) = destructure(&data.fields);
+            field_types.extend(data.fields.iter().map(|field| &field.ty));
+            bytes_arms.push(quote! {
+                #name #pattern => {
+                    #(output.append(&mut #private::AbsorbGadget::to_sponge_bytes(#bindings)?);)*
+                }
+            });
+            elements_arms.push(quote! {
+                #name #pattern => {
+                    #(output.append(
+                        &mut #private::AbsorbGadget::to_sponge_field_elements(#bindings)?
+                    );)*
+                }
+            });
+        }
+        Data::Enum(data) => {
+            for (index, variant) in data.variants.iter().enumerate() {
+                let variant_name = &variant.ident;
+                let index = index as u32;
+                let (pattern, bindings) = destructure(&variant.fields);
+                field_types.extend(variant.fields.iter().map(|field| &field.ty));
+                bytes_arms.push(quote! {
+                    #name::#variant_name #pattern => {
+                        output.append(&mut #private::UInt8::constant_vec(&#index.to_le_bytes()));
+                        #(output.append(&mut #private::AbsorbGadget::to_sponge_bytes(#bindings)?);)*
+                    }
+                });
+                elements_arms.push(quote! {
+                    #name::#variant_name #pattern => {
+                        output.push(#private::FpVar::Constant(__CF::from(#index)));
+                        #(output.append(
+                            &mut #private::AbsorbGadget::to_sponge_field_elements(#bindings)?
+                        );)*
+                    }
+                });
+            }
+        }
+        Data::Union(_) => panic!("`AbsorbGadget` cannot be derived for unions"),
+    }
+
+    let mut generics = ast.generics.clone();
+    generics
+        .params
+        .push(parse_quote! { __CF: #private::PrimeField });
+    let where_clause = generics.make_where_clause();
+    for ty in field_types {
+        where_clause
+            .predicates
+            .push(parse_quote! { #ty: #private::AbsorbGadget<__CF> });
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #private::AbsorbGadget<__CF> for #name #ty_generics #where_clause {
+            #[allow(unused_mut)]
+            fn to_sponge_bytes(
+                &self,
+            ) -> ::core::result::Result<#private::Vec<#private::UInt8<__CF>>, #private::SynthesisError> {
+                let mut output = #private::Vec::new();
+                match self {
+                    #(#bytes_arms)*
+                }
+                ::core::result::Result::Ok(output)
+            }
+
+            #[allow(unused_mut)]
+            fn to_sponge_field_elements(
+                &self,
+            ) -> ::core::result::Result<#private::Vec<#private::FpVar<__CF>>, #private::SynthesisError> {
+                let mut output = #private::Vec::new();
+                match self {
+                    #(#elements_arms)*
+                }
+                ::core::result::Result::Ok(output)
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::alloc::AllocVar;
+use ark_r1cs_std::bits::boolean::Boolean;
+use ark_r1cs_std::bits::uint8::UInt8;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::R1CSVar;
+use ark_relations::ns;
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef};
+use ark_sponge::constraints::AbsorbGadget;
+use ark_sponge::Absorb;
+use ark_test_curves::bls12_381::Fr;
+use std::marker::PhantomData;
+
+#[derive(ark_sponge_derive::AbsorbGadget)]
+struct Witness<F: PrimeField> {
+    value: FpVar<F>,
+    tag: UInt8<F>,
+    flag: Boolean<F>,
+}
+
+#[derive(ark_sponge_derive::AbsorbGadget)]
+struct Tagged<F: PrimeField, T> {
+    value: FpVar<F>,
+    unit: (),
+    marker: PhantomData<T>,
+}
+
+#[derive(ark_sponge_derive::AbsorbGadget)]
+enum Choice<F: PrimeField> {
+    Left(FpVar<F>),
+    Right { value: FpVar<F> },
+}
+
+fn witness(cs: ConstraintSystemRef<Fr>) -> Witness<Fr> {
+    Witness {
+        value: FpVar::new_witness(ns!(cs, "value"), || Ok(Fr::from(7u64))).unwrap(),
+        tag: UInt8::new_witness(ns!(cs, "tag"), || Ok(3u8)).unwrap(),
+        flag: Boolean::new_witness(ns!(cs, "flag"), || Ok(true)).unwrap(),
+    }
+}
+
+#[test]
+fn derived_struct_absorbs_fields_in_order() {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let w = witness(cs.clone());
+
+    let derived = w.to_sponge_field_elements().unwrap();
+    let mut expected = w.value.to_sponge_field_elements().unwrap();
+    expected.append(&mut w.tag.to_sponge_field_elements().unwrap());
+    expected.append(&mut w.flag.to_sponge_field_elements().unwrap());
+    assert_eq!(derived.value().unwrap(), expected.value().unwrap());
+
+    let mut expected_bytes = w.value.to_sponge_bytes().unwrap();
+    expected_bytes.append(&mut w.tag.to_sponge_bytes().unwrap());
+    expected_bytes.append(&mut w.flag.to_sponge_bytes().unwrap());
+    assert_eq!(
+        w.to_sponge_bytes().unwrap().value().unwrap(),
+        expected_bytes.value().unwrap()
+    );
+}
+
+#[test]
+fn derived_enum_prefixes_variant_index() {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let value = FpVar::new_witness(ns!(cs, "value"), || Ok(Fr::from(7u64))).unwrap();
+
+    let left = Choice::Left(value.clone())
+        .to_sponge_field_elements()
+        .unwrap();
+    let right = Choice::Right { value }.to_sponge_field_elements().unwrap();
+    assert_eq!(left.value().unwrap(), vec![Fr::from(0u64), Fr::from(7u64)]);
+    assert_eq!(right.value().unwrap(), vec![Fr::from(1u64), Fr::from(7u64)]);
+
+    let value = FpVar::new_witness(ns!(cs, "value"), || Ok(Fr::from(7u64))).unwrap();
+    let left_bytes = Choice::Left(value.clone()).to_sponge_bytes().unwrap();
+    let right_bytes = Choice::Right { value }.to_sponge_bytes().unwrap();
+    let mut expected = 1u32.to_le_bytes().to_vec();
+    Fr::from(7u64).to_sponge_bytes(&mut expected);
+    assert_eq!(right_bytes.value().unwrap(), expected);
+    assert_ne!(left_bytes.value().unwrap(), right_bytes.value().unwrap());
+}
+
+#[test]
+fn derived_struct_skips_marker_fields() {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let value = FpVar::new_witness(ns!(cs, "value"), || Ok(Fr::from(7u64))).unwrap();
+    let tagged = Tagged::<Fr, String> {
+        value: value.clone(),
+        unit: (),
+        marker: PhantomData,
+    };
+
+    assert_eq!(
+        tagged.to_sponge_field_elements().unwrap().value().unwrap(),
+        value.to_sponge_field_elements().unwrap().value().unwrap()
+    );
+    assert_eq!(
+        tagged.to_sponge_bytes().unwrap().value().unwrap(),
+        value.to_sponge_bytes().unwrap().value().unwrap()
+    );
+}
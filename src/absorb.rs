@@ -1,12 +1,20 @@
 use ark_ec::models::short_weierstrass_jacobian::GroupAffine as SWAffine;
 use ark_ec::models::twisted_edwards_extended::GroupAffine as TEAffine;
 use ark_ec::models::{SWModelParameters, TEModelParameters};
+#[cfg(feature = "snark")]
+use ark_ec::PairingEngine;
 use ark_ff::models::{
-    Fp256, Fp256Parameters, Fp320, Fp320Parameters, Fp384, Fp384Parameters, Fp768, Fp768Parameters,
-    Fp832, Fp832Parameters,
+    CubicExtField, CubicExtParameters, Fp256, Fp256Parameters, Fp320, Fp320Parameters, Fp384,
+    Fp384Parameters, Fp768, Fp768Parameters, Fp832, Fp832Parameters, QuadExtField,
+    QuadExtParameters,
 };
-use ark_ff::{PrimeField, ToConstraintField};
+use ark_ff::{BigInteger, Field, FpParameters, PrimeField, ToConstraintField, Zero};
+#[cfg(feature = "snark")]
+use ark_groth16::Proof;
+#[cfg(feature = "ark-poly")]
+use ark_poly::univariate::DensePolynomial;
 use ark_serialize::CanonicalSerialize;
+use ark_std::time::Duration;
 use ark_std::vec::Vec;
 /// An interface for objects that can be absorbed by a `CryptographicSponge`.
 pub trait Absorb {
@@ -75,6 +83,32 @@ pub trait Absorb {
         }
         result
     }
+
+    /// Specifies the conversion into a list of bytes for a batch nested in a list, which must
+    /// encode the length of the batch. Append the list to `dest`.
+    ///
+    /// By default the length comes first as a `u64`. Types whose `batch_to_sponge_bytes` already
+    /// starts with the length override it, so that the length is not absorbed twice.
+    fn batch_to_sponge_bytes_with_length(batch: &[Self], dest: &mut Vec<u8>)
+    where
+        Self: Sized,
+    {
+        (batch.len() as u64).to_sponge_bytes(dest);
+        Self::batch_to_sponge_bytes(batch, dest)
+    }
+
+    /// Specifies the conversion into a list of field elements for a batch nested in a list, which
+    /// must encode the length of the batch. Append the list to `dest`.
+    ///
+    /// By default the length comes first as a single field element. Types whose
+    /// `batch_to_sponge_field_elements` already starts with the length override it.
+    fn batch_to_sponge_field_elements_with_length<F: PrimeField>(batch: &[Self], dest: &mut Vec<F>)
+    where
+        Self: Sized,
+    {
+        dest.push(F::from(batch.len() as u64));
+        Self::batch_to_sponge_field_elements(batch, dest)
+    }
 }
 
 /// An extension to `Absorb` that is specific to items with variable length, such as a list.
@@ -150,6 +184,13 @@ impl Absorb for u8 {
         bytes.extend_from_slice(batch);
         dest.extend_from_slice(&bytes.to_field_elements().unwrap()[..])
     }
+
+    fn batch_to_sponge_field_elements_with_length<F: PrimeField>(
+        batch: &[Self],
+        dest: &mut Vec<F>,
+    ) {
+        Self::batch_to_sponge_field_elements(batch, dest)
+    }
 }
 
 impl Absorb for bool {
@@ -252,6 +293,43 @@ impl Absorb for isize {
     }
 }
 
+/// An element of a quadratic extension absorbs its coefficients `c0` and `c1`, in that order.
+///
+/// Together with the impl for cubic extensions, this covers towers of extensions recursively,
+/// such as the target group `E::Fqk` of a pairing.
+impl<P: QuadExtParameters> Absorb for QuadExtField<P>
+where
+    P::BaseField: Absorb,
+{
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        self.c0.to_sponge_bytes(dest);
+        self.c1.to_sponge_bytes(dest)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        self.c0.to_sponge_field_elements(dest);
+        self.c1.to_sponge_field_elements(dest)
+    }
+}
+
+/// An element of a cubic extension absorbs its coefficients `c0`, `c1` and `c2`, in that order.
+impl<P: CubicExtParameters> Absorb for CubicExtField<P>
+where
+    P::BaseField: Absorb,
+{
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        self.c0.to_sponge_bytes(dest);
+        self.c1.to_sponge_bytes(dest);
+        self.c2.to_sponge_bytes(dest)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        self.c0.to_sponge_field_elements(dest);
+        self.c1.to_sponge_field_elements(dest);
+        self.c2.to_sponge_field_elements(dest)
+    }
+}
+
 impl<CF: PrimeField, P: TEModelParameters<BaseField = CF>> Absorb for TEAffine<P> {
     fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
         self.to_field_elements().unwrap().serialize(dest).unwrap()
@@ -260,18 +338,361 @@ impl<CF: PrimeField, P: TEModelParameters<BaseField = CF>> Absorb for TEAffine<P
     fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
         batch_field_cast::<P::BaseField, _>(&self.to_field_elements().unwrap(), dest).unwrap();
     }
+
+    fn batch_to_sponge_bytes(batch: &[Self], dest: &mut Vec<u8>) {
+        (batch.len() as u64).to_sponge_bytes(dest);
+        for point in batch {
+            point.to_sponge_bytes(dest)
+        }
+    }
+
+    fn batch_to_sponge_field_elements<F: PrimeField>(batch: &[Self], dest: &mut Vec<F>) {
+        dest.push(F::from(batch.len() as u64));
+        for point in batch {
+            point.to_sponge_field_elements(dest)
+        }
+    }
+
+    fn batch_to_sponge_bytes_with_length(batch: &[Self], dest: &mut Vec<u8>) {
+        Self::batch_to_sponge_bytes(batch, dest)
+    }
+
+    fn batch_to_sponge_field_elements_with_length<F: PrimeField>(
+        batch: &[Self],
+        dest: &mut Vec<F>,
+    ) {
+        Self::batch_to_sponge_field_elements(batch, dest)
+    }
 }
 
-impl<CF: PrimeField, P: SWModelParameters<BaseField = CF>> Absorb for SWAffine<P> {
+/// A point absorbs its coordinates followed by its infinity flag. Over an extension field, such
+/// as `G2` of a pairing, each coordinate absorbs its coefficients in the base prime field.
+impl<P: SWModelParameters> Absorb for SWAffine<P>
+where
+    P::BaseField: ToConstraintField<<P::BaseField as Field>::BasePrimeField>,
+{
     fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
-        self.to_field_elements().unwrap().serialize(dest).unwrap()
+        let elements: Vec<<P::BaseField as Field>::BasePrimeField> =
+            self.to_field_elements().unwrap();
+        elements.serialize(dest).unwrap()
     }
 
     fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
-        batch_field_cast::<P::BaseField, _>(&self.to_field_elements().unwrap(), dest).unwrap();
+        let elements: Vec<<P::BaseField as Field>::BasePrimeField> =
+            self.to_field_elements().unwrap();
+        batch_field_cast(&elements, dest).unwrap();
+    }
+
+    fn batch_to_sponge_bytes(batch: &[Self], dest: &mut Vec<u8>) {
+        (batch.len() as u64).to_sponge_bytes(dest);
+        for point in batch {
+            point.to_sponge_bytes(dest)
+        }
+    }
+
+    fn batch_to_sponge_field_elements<F: PrimeField>(batch: &[Self], dest: &mut Vec<F>) {
+        dest.push(F::from(batch.len() as u64));
+        for point in batch {
+            point.to_sponge_field_elements(dest)
+        }
+    }
+
+    fn batch_to_sponge_bytes_with_length(batch: &[Self], dest: &mut Vec<u8>) {
+        Self::batch_to_sponge_bytes(batch, dest)
+    }
+
+    fn batch_to_sponge_field_elements_with_length<F: PrimeField>(
+        batch: &[Self],
+        dest: &mut Vec<F>,
+    ) {
+        Self::batch_to_sponge_field_elements(batch, dest)
+    }
+}
+
+/// Absorbs the number of coefficients, followed by the coefficients from the constant term
+/// up. The number of coefficients keeps a polynomial apart from the same polynomial padded
+/// with zero coefficients. As bytes, it is a little-endian `u64`.
+#[cfg(feature = "ark-poly")]
+impl<CF: PrimeField> Absorb for DensePolynomial<CF> {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        (self.coeffs.len() as u64).to_sponge_bytes(dest);
+        for coeff in &self.coeffs {
+            coeff.serialize(&mut *dest).unwrap();
+        }
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        dest.push(F::from(self.coeffs.len() as u64));
+        batch_field_cast(&self.coeffs, dest).unwrap();
+    }
+}
+
+/// A Groth16 proof absorbs its elements `A`, `B` and `C`, in that order.
+#[cfg(feature = "snark")]
+impl<E: PairingEngine> Absorb for Proof<E>
+where
+    E::G1Affine: Absorb,
+    E::G2Affine: Absorb,
+{
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        self.a.to_sponge_bytes(dest);
+        self.b.to_sponge_bytes(dest);
+        self.c.to_sponge_bytes(dest)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        self.a.to_sponge_field_elements(dest);
+        self.b.to_sponge_field_elements(dest);
+        self.c.to_sponge_field_elements(dest)
+    }
+}
+
+/// A wrapper that absorbs a short Weierstrass affine point in compressed form.
+///
+/// Instead of the two coordinates and the infinity flag, a compressed point absorbs its
+/// x-coordinate followed by a single flag element, whose bit 0 is the parity of `y` and whose
+/// bit 1 is set for the point at infinity. This takes two field elements instead of three.
+///
+/// The point at infinity has no coordinates of its own: both `GroupAffine::zero()` and the
+/// gadget `ProjectiveVar::to_affine` represent it as `(0, 1)`, which keeps its encoding
+/// deterministic. An affine point built by hand with the infinity flag set and any other
+/// coordinates absorbs differently.
+#[derive(Clone, Debug)]
+pub struct Compressed<G>(pub G);
+
+impl<CF: PrimeField, P: SWModelParameters<BaseField = CF>> Compressed<SWAffine<P>> {
+    fn flags(&self) -> u8 {
+        (self.0.y.into_repr().is_odd() as u8) | ((self.0.infinity as u8) << 1)
     }
 }
 
+impl<CF: PrimeField, P: SWModelParameters<BaseField = CF>> Absorb for Compressed<SWAffine<P>> {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        self.0.x.serialize(&mut *dest).unwrap();
+        dest.push(self.flags())
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        dest.push(field_cast(self.0.x).unwrap());
+        dest.push(F::from(self.flags()))
+    }
+}
+
+/// A wrapper that absorbs a twisted Edwards affine point `(x, y)` through the coordinates
+/// `(u, v)` of the corresponding point on the birationally equivalent Montgomery curve, where
+/// `u = (1 + y) / (1 - y)` and `v = u / x`.
+///
+/// The map is undefined at the two points with `x = 0`. Like
+/// `MontgomeryAffineVar::from_edwards_to_coords` in `ark-r1cs-std`, the identity `(0, 1)` is
+/// encoded as `(0, 1)` and the point of order two `(0, -1)` as `(0, 0)`, so neither collides with
+/// the encoding of another point.
+#[derive(Clone, Debug)]
+pub struct Montgomery<G>(pub G);
+
+impl<CF: PrimeField, P: TEModelParameters<BaseField = CF>> Montgomery<TEAffine<P>> {
+    /// Returns the Montgomery coordinates `(u, v)` of the point.
+    pub fn coordinates(&self) -> [CF; 2] {
+        let TEAffine { x, y, .. } = self.0;
+        if y.is_one() {
+            [CF::zero(), CF::one()]
+        } else if x.is_zero() {
+            [CF::zero(), CF::zero()]
+        } else {
+            let u = (CF::one() + y) / (CF::one() - y);
+            [u, u / x]
+        }
+    }
+}
+
+impl<CF: PrimeField, P: TEModelParameters<BaseField = CF>> Absorb for Montgomery<TEAffine<P>> {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        let [u, v] = self.coordinates();
+        u.serialize(&mut *dest).unwrap();
+        v.serialize(dest).unwrap()
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        let [u, v] = self.coordinates();
+        batch_field_cast(&[u, v], dest).unwrap();
+    }
+}
+
+/// A wrapper that absorbs an affine point as an explicit infinity flag followed by its
+/// coordinates `(x, y)`.
+///
+/// The flag is 1 for the point at infinity of a short Weierstrass curve and for the identity
+/// `(0, 1)` of a twisted Edwards curve, and 0 for every other point. The coordinates of such a
+/// point are absorbed as zeros whatever its representation, so it absorbs as `[1, 0, 0]`, which
+/// no other point shares, even one with zero coordinates. Bytes are the flag as a single byte
+/// followed by the serialized coordinates.
+#[derive(Clone, Debug)]
+pub struct WithInfinityFlag<G>(pub G);
+
+/// Returns `[flag, x, y]`, with the coordinates zeroed out at infinity.
+fn flagged_coordinates<CF: PrimeField>(is_infinity: bool, x: CF, y: CF) -> [CF; 3] {
+    if is_infinity {
+        [CF::one(), CF::zero(), CF::zero()]
+    } else {
+        [CF::zero(), x, y]
+    }
+}
+
+fn flagged_to_sponge_bytes<CF: PrimeField>(is_infinity: bool, x: CF, y: CF, dest: &mut Vec<u8>) {
+    let [_, x, y] = flagged_coordinates(is_infinity, x, y);
+    dest.push(is_infinity as u8);
+    x.serialize(&mut *dest).unwrap();
+    y.serialize(dest).unwrap()
+}
+
+impl<CF: PrimeField, P: SWModelParameters<BaseField = CF>> Absorb
+    for WithInfinityFlag<SWAffine<P>>
+{
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        flagged_to_sponge_bytes(self.0.infinity, self.0.x, self.0.y, dest)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        let elements = flagged_coordinates(self.0.infinity, self.0.x, self.0.y);
+        batch_field_cast(&elements, dest).unwrap();
+    }
+}
+
+impl<CF: PrimeField, P: TEModelParameters<BaseField = CF>> Absorb
+    for WithInfinityFlag<TEAffine<P>>
+{
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        flagged_to_sponge_bytes(self.0.is_zero(), self.0.x, self.0.y, dest)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        let elements = flagged_coordinates(self.0.is_zero(), self.0.x, self.0.y);
+        batch_field_cast(&elements, dest).unwrap();
+    }
+}
+
+/// A wrapper that absorbs an unsigned integer of arbitrary size, given by its little-endian
+/// `bits`, as limbs of `limb_bits` bits each.
+///
+/// The integer is split into limbs starting from the least significant bit, and only the last
+/// limb may be shorter. It absorbs the number of limbs, followed by the limbs from the least to
+/// the most significant one. As field elements, each limb is one element; as bytes, the number
+/// of limbs is a little-endian `u64` and each limb takes `ceil(limb_bits / 8)` little-endian
+/// bytes. The number of limbs keeps integers of different bit lengths apart, even when their
+/// low limbs agree.
+///
+/// Absorbing as field elements panics if `limb_bits` exceeds the capacity of the field.
+#[derive(Clone, Debug)]
+pub struct Limbs<B> {
+    /// The bits of the integer, least significant first.
+    pub bits: B,
+    /// The number of bits of every limb but the last one.
+    pub limb_bits: usize,
+}
+
+impl<B> Limbs<B> {
+    /// Wraps the little-endian `bits` of an integer, to be absorbed in limbs of `limb_bits`
+    /// bits. Panics if `limb_bits` is zero.
+    pub fn new(bits: B, limb_bits: usize) -> Self {
+        assert!(limb_bits > 0, "limbs must have at least one bit");
+        Self { bits, limb_bits }
+    }
+}
+
+impl Absorb for Limbs<Vec<bool>> {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        let limbs = self.bits.chunks(self.limb_bits);
+        (limbs.len() as u64).to_sponge_bytes(dest);
+        for limb in limbs {
+            let mut limb = limb.to_vec();
+            limb.resize((self.limb_bits + 7) / 8 * 8, false);
+            for byte in limb.chunks(8) {
+                dest.push(
+                    byte.iter()
+                        .rev()
+                        .fold(0, |acc, bit| (acc << 1) | *bit as u8),
+                );
+            }
+        }
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        assert!(
+            self.limb_bits <= F::Params::CAPACITY as usize,
+            "limbs of {} bits do not fit in a single field element",
+            self.limb_bits
+        );
+        let limbs = self.bits.chunks(self.limb_bits);
+        dest.push(F::from(limbs.len() as u64));
+        for limb in limbs {
+            dest.push(F::from_repr(F::BigInt::from_bits_le(limb)).unwrap());
+        }
+    }
+}
+
+/// A wrapper that absorbs a byte message in blocks, as specifications that pack messages into
+/// field elements usually do.
+///
+/// As field elements, the number of bytes comes first. The message follows in blocks of
+/// `floor(F::Params::CAPACITY / 8)` bytes, 31 bytes for a 255-bit field. Each block is one
+/// element, read as a little-endian integer, so that the final short block is implicitly padded
+/// with zeros. The length keeps messages that only differ by trailing zeros in their final
+/// block apart. As bytes, the message is absorbed as is, after its length as a little-endian
+/// `u64`.
+#[derive(Clone, Debug)]
+pub struct Message<B>(pub B);
+
+impl<B: AsRef<[u8]>> Absorb for Message<B> {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        let message = self.0.as_ref();
+        (message.len() as u64).to_sponge_bytes(dest);
+        dest.extend_from_slice(message)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        let message = self.0.as_ref();
+        let block_len = (F::Params::CAPACITY / 8) as usize;
+        dest.push(F::from(message.len() as u64));
+        for block in message.chunks(block_len) {
+            dest.push(F::from_le_bytes_mod_order(block));
+        }
+    }
+}
+
+/// The byte order of a serialized field element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    /// The least significant byte comes first, as in `CanonicalSerialize`.
+    Little,
+    /// The most significant byte comes first.
+    Big,
+}
+
+/// A wrapper that absorbs a field element whose bytes are serialized in the given byte order.
+///
+/// Only the bytes are affected, for interoperability with hashers that serialize field elements
+/// big-endian before packing them. As field elements, the element is absorbed as is.
+#[derive(Clone, Debug)]
+pub struct WithEndianness<T>(pub T, pub Endianness);
+
+impl<CF: PrimeField> Absorb for WithEndianness<CF> {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        let mut bytes = self.0.into_repr().to_bytes_le();
+        if self.1 == Endianness::Big {
+            bytes.reverse();
+        }
+        dest.append(&mut bytes)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        batch_field_cast(&[self.0], dest).unwrap();
+    }
+}
+
+/// A list of lists, such as a `&[&[A]]` or a `Vec<Vec<A>>`, absorbs its number of lists, then
+/// the length and the contents of each inner list, so that lists of different shapes never
+/// absorb the same data. The lengths are `u64`s, as little-endian bytes or as single field
+/// elements. An inner list whose own encoding already starts with its length does not absorb it
+/// twice: see `Absorb::batch_to_sponge_bytes_with_length`.
 impl<A: Absorb> Absorb for &[A] {
     fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
         A::batch_to_sponge_bytes(self, dest)
@@ -280,6 +701,31 @@ impl<A: Absorb> Absorb for &[A] {
     fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
         A::batch_to_sponge_field_elements(self, dest)
     }
+
+    fn batch_to_sponge_bytes(batch: &[Self], dest: &mut Vec<u8>) {
+        (batch.len() as u64).to_sponge_bytes(dest);
+        for item in batch {
+            A::batch_to_sponge_bytes_with_length(item, dest)
+        }
+    }
+
+    fn batch_to_sponge_field_elements<F: PrimeField>(batch: &[Self], dest: &mut Vec<F>) {
+        dest.push(F::from(batch.len() as u64));
+        for item in batch {
+            A::batch_to_sponge_field_elements_with_length(item, dest)
+        }
+    }
+
+    fn batch_to_sponge_bytes_with_length(batch: &[Self], dest: &mut Vec<u8>) {
+        Self::batch_to_sponge_bytes(batch, dest)
+    }
+
+    fn batch_to_sponge_field_elements_with_length<F: PrimeField>(
+        batch: &[Self],
+        dest: &mut Vec<F>,
+    ) {
+        Self::batch_to_sponge_field_elements(batch, dest)
+    }
 }
 
 impl<A: Absorb> AbsorbWithLength for &[A] {
@@ -288,6 +734,7 @@ impl<A: Absorb> AbsorbWithLength for &[A] {
     }
 }
 
+/// Absorbs like a `&[A]`, including when nested in a list.
 impl<A: Absorb> Absorb for Vec<A> {
     fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
         self.as_slice().to_sponge_bytes(dest)
@@ -296,6 +743,31 @@ impl<A: Absorb> Absorb for Vec<A> {
     fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
         self.as_slice().to_sponge_field_elements(dest)
     }
+
+    fn batch_to_sponge_bytes(batch: &[Self], dest: &mut Vec<u8>) {
+        (batch.len() as u64).to_sponge_bytes(dest);
+        for item in batch {
+            A::batch_to_sponge_bytes_with_length(item, dest)
+        }
+    }
+
+    fn batch_to_sponge_field_elements<F: PrimeField>(batch: &[Self], dest: &mut Vec<F>) {
+        dest.push(F::from(batch.len() as u64));
+        for item in batch {
+            A::batch_to_sponge_field_elements_with_length(item, dest)
+        }
+    }
+
+    fn batch_to_sponge_bytes_with_length(batch: &[Self], dest: &mut Vec<u8>) {
+        Self::batch_to_sponge_bytes(batch, dest)
+    }
+
+    fn batch_to_sponge_field_elements_with_length<F: PrimeField>(
+        batch: &[Self],
+        dest: &mut Vec<F>,
+    ) {
+        Self::batch_to_sponge_field_elements(batch, dest)
+    }
 }
 
 impl<A: Absorb> AbsorbWithLength for Vec<A> {
@@ -304,6 +776,22 @@ impl<A: Absorb> AbsorbWithLength for Vec<A> {
     }
 }
 
+impl<A: Absorb, const N: usize> Absorb for [A; N] {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        self.as_ref().to_sponge_bytes(dest)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        self.as_ref().to_sponge_field_elements(dest)
+    }
+}
+
+impl<A: Absorb, const N: usize> AbsorbWithLength for [A; N] {
+    fn absorb_length(&self) -> usize {
+        N
+    }
+}
+
 impl<A: Absorb> Absorb for Option<A> {
     fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
         self.is_some().to_sponge_bytes(dest);
@@ -320,6 +808,30 @@ impl<A: Absorb> Absorb for Option<A> {
     }
 }
 
+impl<A: Absorb, B: Absorb> Absorb for (A, B) {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        self.0.to_sponge_bytes(dest);
+        self.1.to_sponge_bytes(dest)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        self.0.to_sponge_field_elements(dest);
+        self.1.to_sponge_field_elements(dest)
+    }
+}
+
+/// A `Duration` is absorbed as the pair `(as_secs(), subsec_nanos())`, that is, as a `u64`
+/// followed by a `u32`. Both are encoded in little-endian, independently of the platform.
+impl Absorb for Duration {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        (self.as_secs(), self.subsec_nanos()).to_sponge_bytes(dest)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        (self.as_secs(), self.subsec_nanos()).to_sponge_field_elements(dest)
+    }
+}
+
 // TODO: add more for common data structures, treemap?
 
 impl<A: Absorb> Absorb for &A {
@@ -373,12 +885,131 @@ macro_rules! collect_sponge_field_elements {
 }
 
 #[cfg(test)]
-mod tests {
-    use crate::{batch_field_cast, field_cast};
-    use ark_ff::UniformRand;
+pub(crate) mod tests {
+    use crate::{batch_field_cast, field_cast, Absorb, Message, Montgomery};
+    use ark_ec::models::twisted_edwards_extended::GroupAffine as TEAffine;
+    use ark_ec::models::{ModelParameters, MontgomeryModelParameters, TEModelParameters};
+    use ark_ec::{AffineCurve, ProjectiveCurve};
+    use ark_ff::{
+        field_new, BigInteger, Field, Fp2, Fp2Parameters, One, PrimeField, UniformRand, Zero,
+    };
     use ark_std::test_rng;
+    use ark_std::time::Duration;
+    use ark_std::vec;
     use ark_std::vec::Vec;
-    use ark_test_curves::bls12_381::Fr;
+    use ark_test_curves::bls12_381::{Fq, Fr};
+
+    /// The quadratic extension `Fq[u] / (u^2 + 1)` of the BLS12-381 base field.
+    pub(crate) struct Fq2Parameters;
+
+    impl Fp2Parameters for Fq2Parameters {
+        type Fp = Fq;
+
+        const NONRESIDUE: Fq = field_new!(Fq, "-1");
+        const QUADRATIC_NONRESIDUE: (Fq, Fq) = (field_new!(Fq, "1"), field_new!(Fq, "1"));
+        const FROBENIUS_COEFF_FP2_C1: &'static [Fq] = &[field_new!(Fq, "1"), field_new!(Fq, "-1")];
+    }
+
+    pub(crate) type Fq2 = Fp2<Fq2Parameters>;
+
+    /// The Jubjub curve `-x^2 + y^2 = 1 - (10240 / 10241) x^2 y^2` over the BLS12-381 scalar
+    /// field. Its own scalar field is not available here, so `Fr` stands in for it: the tests
+    /// only use the group law, which does not depend on it.
+    #[derive(Clone, Default, PartialEq, Eq)]
+    pub(crate) struct JubjubParameters;
+
+    impl ModelParameters for JubjubParameters {
+        type BaseField = Fr;
+        type ScalarField = Fr;
+    }
+
+    impl TEModelParameters for JubjubParameters {
+        const COEFF_A: Fr = field_new!(Fr, "-1");
+        const COEFF_D: Fr = field_new!(
+            Fr,
+            "19257038036680949359750312669786877991949435402254120286184196891950884077233"
+        );
+        const COFACTOR: &'static [u64] = &[8];
+        const COFACTOR_INV: Fr = field_new!(Fr, "1");
+        const AFFINE_GENERATOR_COEFFS: (Fr, Fr) = (
+            field_new!(
+                Fr,
+                "947480793030046690521372793511108844824410854817750647956076029995258996586"
+            ),
+            field_new!(Fr, "3"),
+        );
+
+        type MontgomeryModelParameters = JubjubParameters;
+    }
+
+    impl MontgomeryModelParameters for JubjubParameters {
+        const COEFF_A: Fr = field_new!(Fr, "40962");
+        const COEFF_B: Fr = field_new!(Fr, "-40964");
+
+        type TEModelParameters = JubjubParameters;
+    }
+
+    pub(crate) type Jubjub = TEAffine<JubjubParameters>;
+
+    /// A random point, followed by the identity and the point of order two.
+    pub(crate) fn jubjub_test_points() -> Vec<Jubjub> {
+        let generator = Jubjub::prime_subgroup_generator();
+        vec![
+            generator.mul(Fr::rand(&mut test_rng())).into_affine(),
+            Jubjub::zero(),
+            Jubjub::new(Fr::zero(), -Fr::one()),
+        ]
+    }
+
+    #[cfg(feature = "ark-poly")]
+    #[test]
+    fn test_dense_polynomial() {
+        use ark_poly::univariate::DensePolynomial;
+
+        let coeffs = vec![Fr::from(1u64), Fr::from(2u64)];
+        let poly = DensePolynomial { coeffs };
+        let mut padded = poly.clone();
+        padded.coeffs.push(Fr::zero());
+
+        let elements = poly.to_sponge_field_elements_as_vec::<Fr>();
+        assert_eq!(
+            elements,
+            vec![Fr::from(2u64), Fr::from(1u64), Fr::from(2u64)]
+        );
+        assert_ne!(elements, padded.to_sponge_field_elements_as_vec::<Fr>());
+        assert_ne!(
+            poly.to_sponge_bytes_as_vec(),
+            padded.to_sponge_bytes_as_vec()
+        );
+    }
+
+    #[test]
+    fn test_message_blocks() {
+        // "abc" fits in a single block, read as the little-endian integer 0x636261.
+        assert_eq!(
+            Message(b"abc").to_sponge_field_elements_as_vec::<Fr>(),
+            vec![Fr::from(3u64), Fr::from(0x636261u64)]
+        );
+
+        // 32 bytes take a full block of 31 bytes and a block made of the last byte.
+        let message = [0xffu8; 32];
+        let elements = Message(message).to_sponge_field_elements_as_vec::<Fr>();
+        let full_block = (0..31 * 8).fold(Fr::zero(), |acc, _| acc.double() + Fr::one());
+        assert_eq!(
+            elements,
+            vec![Fr::from(32u64), full_block, Fr::from(0xffu64)]
+        );
+
+        // A trailing zero does not change the blocks, but it changes the length.
+        assert_ne!(
+            Message(b"abc\0").to_sponge_field_elements_as_vec::<Fr>(),
+            Message(b"abc").to_sponge_field_elements_as_vec::<Fr>()
+        );
+        assert_eq!(
+            Message(b"abc").to_sponge_bytes_as_vec(),
+            vec![3, 0, 0, 0, 0, 0, 0, 0, b'a', b'b', b'c']
+        );
+    }
 
     #[test]
     fn test_cast() {
@@ -391,4 +1022,120 @@ mod tests {
         batch_field_cast::<_, Fr>(&expected, &mut actual).unwrap();
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_timestamp_encoding() {
+        let seconds = 0x0102_0304_0506_0708u64;
+        let nanos = 0x0a0b_0c0du32;
+        let expected = vec![
+            0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x0d, 0x0c, 0x0b, 0x0a,
+        ];
+
+        assert_eq!((seconds, nanos).to_sponge_bytes_as_vec(), expected);
+        assert_eq!(
+            Duration::new(seconds, nanos).to_sponge_bytes_as_vec(),
+            expected
+        );
+        assert_eq!(
+            Duration::new(seconds, nanos).to_sponge_field_elements_as_vec::<Fr>(),
+            vec![Fr::from(seconds), Fr::from(nanos)]
+        );
+    }
+
+    #[cfg(feature = "snark")]
+    #[test]
+    fn test_groth16_proof() {
+        use ark_bn254::{Bn254, Fq, G1Affine, G2Affine};
+        use ark_groth16::Proof;
+
+        let g1 = G1Affine::prime_subgroup_generator();
+        let g2 = G2Affine::prime_subgroup_generator();
+        let proof = Proof::<Bn254> {
+            a: g1,
+            b: g2.mul(3u64).into_affine(),
+            c: g1.mul(5u64).into_affine(),
+        };
+
+        let mut expected = proof.a.to_sponge_field_elements_as_vec::<Fq>();
+        proof.b.to_sponge_field_elements(&mut expected);
+        proof.c.to_sponge_field_elements(&mut expected);
+        // `B` contributes its two coordinates in `Fq2` and its infinity flag.
+        assert_eq!(expected.len(), 3 + 5 + 3);
+        assert_eq!(proof.to_sponge_field_elements_as_vec::<Fq>(), expected);
+
+        let mut expected = proof.a.to_sponge_bytes_as_vec();
+        proof.b.to_sponge_bytes(&mut expected);
+        proof.c.to_sponge_bytes(&mut expected);
+        assert_eq!(proof.to_sponge_bytes_as_vec(), expected);
+    }
+
+    #[test]
+    fn test_quadratic_extension() {
+        let mut rng = test_rng();
+        let elem = Fq2::rand(&mut rng);
+
+        assert_eq!(
+            elem.to_sponge_field_elements_as_vec::<Fq>(),
+            vec![elem.c0, elem.c1]
+        );
+
+        let mut expected = elem.c0.to_sponge_bytes_as_vec();
+        elem.c1.to_sponge_bytes(&mut expected);
+        assert_eq!(elem.to_sponge_bytes_as_vec(), expected);
+    }
+
+    #[test]
+    fn test_montgomery_form() {
+        let coefficient_a = <JubjubParameters as MontgomeryModelParameters>::COEFF_A;
+        let coefficient_b = <JubjubParameters as MontgomeryModelParameters>::COEFF_B;
+
+        let points = jubjub_test_points();
+        let [u, v] = Montgomery(points[0]).coordinates();
+        assert_eq!(
+            coefficient_b * v.square(),
+            u * u.square() + coefficient_a * u.square() + u
+        );
+        assert_eq!(Montgomery(points[1]).coordinates(), [Fr::zero(), Fr::one()]);
+        assert_eq!(
+            Montgomery(points[2]).coordinates(),
+            [Fr::zero(), Fr::zero()]
+        );
+
+        for point in points {
+            let [u, v] = Montgomery(point).coordinates();
+            assert_eq!(
+                Montgomery(point).to_sponge_field_elements_as_vec::<Fr>(),
+                vec![u, v]
+            );
+            let mut bytes = u.into_repr().to_bytes_le();
+            bytes.append(&mut v.into_repr().to_bytes_le());
+            assert_eq!(Montgomery(point).to_sponge_bytes_as_vec(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_pairing_output() {
+        use ark_bn254::{Bn254, G1Affine, G2Affine};
+        use ark_ec::PairingEngine;
+
+        let g1 = G1Affine::prime_subgroup_generator();
+        let g2 = G2Affine::prime_subgroup_generator();
+        let gt = Bn254::pairing(g1, g2);
+        let same = Bn254::pairing(g1, g2);
+        let other = Bn254::pairing(g1.mul(2u64).into_affine(), g2);
+        assert_eq!(other, gt.square());
+
+        let elements = gt.to_sponge_field_elements_as_vec::<ark_bn254::Fq>();
+        assert_eq!(elements.len(), 12);
+        assert_eq!(
+            same.to_sponge_field_elements_as_vec::<ark_bn254::Fq>(),
+            elements
+        );
+        assert_eq!(same.to_sponge_bytes_as_vec(), gt.to_sponge_bytes_as_vec());
+        assert_ne!(
+            other.to_sponge_field_elements_as_vec::<ark_bn254::Fq>(),
+            elements
+        );
+        assert_ne!(other.to_sponge_bytes_as_vec(), gt.to_sponge_bytes_as_vec());
+    }
 }
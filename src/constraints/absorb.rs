@@ -1,15 +1,27 @@
+use crate::{Compressed, Endianness, Limbs, Message, Montgomery, WithEndianness, WithInfinityFlag};
+#[cfg(feature = "snark")]
+use ark_ec::PairingEngine;
 use ark_ec::{ModelParameters, SWModelParameters, TEModelParameters};
-use ark_ff::{Field, PrimeField};
+use ark_ff::{Field, FpParameters, PrimeField};
+#[cfg(feature = "snark")]
+use ark_groth16::constraints::ProofVar;
 use ark_r1cs_std::bits::boolean::Boolean;
 use ark_r1cs_std::bits::uint8::UInt8;
+use ark_r1cs_std::fields::cubic_extension::{CubicExtVar, CubicExtVarParams};
 use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::fields::quadratic_extension::{QuadExtVar, QuadExtVarParams};
 use ark_r1cs_std::fields::{FieldOpsBounds, FieldVar};
 use ark_r1cs_std::groups::curves::short_weierstrass::{
     AffineVar as SWAffineVar, ProjectiveVar as SWProjectiveVar,
 };
 use ark_r1cs_std::groups::curves::twisted_edwards::AffineVar as TEAffineVar;
-use ark_r1cs_std::{ToBytesGadget, ToConstraintFieldGadget};
+#[cfg(feature = "snark")]
+use ark_r1cs_std::pairing::PairingVar;
+#[cfg(feature = "ark-poly")]
+use ark_r1cs_std::poly::polynomial::univariate::dense::DensePolynomialVar;
+use ark_r1cs_std::{ToBitsGadget, ToBytesGadget, ToConstraintFieldGadget};
 use ark_relations::r1cs::SynthesisError;
+use ark_std::marker::PhantomData;
 use ark_std::vec;
 use ark_std::vec::Vec;
 /// An interface for objects that can be absorbed by a `CryptographicSpongeVar` whose constraint field
@@ -34,6 +46,15 @@ pub trait AbsorbGadget<F: PrimeField> {
     /// Converts the object into field elements that can be absorbed by a `CryptographicSpongeVar`.
     fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError>;
 
+    /// Appends the field elements of `to_sponge_field_elements` to `dest`.
+    ///
+    /// By default this allocates them and moves them over. Types that can write their elements
+    /// to `dest` directly override it.
+    fn to_sponge_field_elements_in(&self, dest: &mut Vec<FpVar<F>>) -> Result<(), SynthesisError> {
+        dest.append(&mut self.to_sponge_field_elements()?);
+        Ok(())
+    }
+
     /// Specifies the conversion into a list of field elements for a batch.
     fn batch_to_sponge_field_elements(batch: &[Self]) -> Result<Vec<FpVar<F>>, SynthesisError>
     where
@@ -46,6 +67,47 @@ pub trait AbsorbGadget<F: PrimeField> {
 
         Ok(output)
     }
+
+    /// Appends the field elements of `batch_to_sponge_field_elements` to `dest`.
+    ///
+    /// By default this allocates them and moves them over, as `to_sponge_field_elements_in`
+    /// does. Types that can write a batch to `dest` directly override it.
+    fn batch_to_sponge_field_elements_in(
+        batch: &[Self],
+        dest: &mut Vec<FpVar<F>>,
+    ) -> Result<(), SynthesisError>
+    where
+        Self: Sized,
+    {
+        dest.append(&mut Self::batch_to_sponge_field_elements(batch)?);
+        Ok(())
+    }
+
+    /// Specifies the conversion into a list of bytes for a batch nested in a list, which must
+    /// encode the length of the batch, as in `Absorb::batch_to_sponge_bytes_with_length`.
+    fn batch_to_sponge_bytes_with_length(batch: &[Self]) -> Result<Vec<UInt8<F>>, SynthesisError>
+    where
+        Self: Sized,
+    {
+        // The length is statically known, so it is a constant.
+        let mut output = UInt8::constant_vec(&(batch.len() as u64).to_le_bytes());
+        output.append(&mut Self::batch_to_sponge_bytes(batch)?);
+        Ok(output)
+    }
+
+    /// Specifies the conversion into a list of field elements for a batch nested in a list, which
+    /// must encode the length of the batch, as in
+    /// `Absorb::batch_to_sponge_field_elements_with_length`.
+    fn batch_to_sponge_field_elements_with_length(
+        batch: &[Self],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError>
+    where
+        Self: Sized,
+    {
+        let mut output = vec![FpVar::Constant(F::from(batch.len() as u64))];
+        output.append(&mut Self::batch_to_sponge_field_elements(batch)?);
+        Ok(output)
+    }
 }
 
 impl<F: PrimeField> AbsorbGadget<F> for UInt8<F> {
@@ -64,6 +126,12 @@ impl<F: PrimeField> AbsorbGadget<F> for UInt8<F> {
         bytes.extend_from_slice(batch);
         bytes.to_constraint_field()
     }
+
+    fn batch_to_sponge_field_elements_with_length(
+        batch: &[Self],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Self::batch_to_sponge_field_elements(batch)
+    }
 }
 
 impl<F: PrimeField> AbsorbGadget<F> for Boolean<F> {
@@ -74,6 +142,19 @@ impl<F: PrimeField> AbsorbGadget<F> for Boolean<F> {
     fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
         Ok(vec![FpVar::from(self.clone())])
     }
+
+    fn to_sponge_field_elements_in(&self, dest: &mut Vec<FpVar<F>>) -> Result<(), SynthesisError> {
+        dest.push(FpVar::from(self.clone()));
+        Ok(())
+    }
+
+    fn batch_to_sponge_field_elements_in(
+        batch: &[Self],
+        dest: &mut Vec<FpVar<F>>,
+    ) -> Result<(), SynthesisError> {
+        dest.extend(batch.iter().cloned().map(FpVar::from));
+        Ok(())
+    }
 }
 
 impl<F: PrimeField> AbsorbGadget<F> for FpVar<F> {
@@ -85,9 +166,22 @@ impl<F: PrimeField> AbsorbGadget<F> for FpVar<F> {
         Ok(vec![self.clone()])
     }
 
+    fn to_sponge_field_elements_in(&self, dest: &mut Vec<FpVar<F>>) -> Result<(), SynthesisError> {
+        dest.push(self.clone());
+        Ok(())
+    }
+
     fn batch_to_sponge_field_elements(batch: &[Self]) -> Result<Vec<FpVar<F>>, SynthesisError> {
         Ok(batch.to_vec())
     }
+
+    fn batch_to_sponge_field_elements_in(
+        batch: &[Self],
+        dest: &mut Vec<FpVar<F>>,
+    ) -> Result<(), SynthesisError> {
+        dest.extend_from_slice(batch);
+        Ok(())
+    }
 }
 
 macro_rules! impl_absorbable_group {
@@ -110,6 +204,41 @@ macro_rules! impl_absorbable_group {
             ) -> Result<Vec<FpVar<<P::BaseField as Field>::BasePrimeField>>, SynthesisError> {
                 self.to_constraint_field()
             }
+
+            fn batch_to_sponge_bytes(
+                batch: &[Self],
+            ) -> Result<Vec<UInt8<<P::BaseField as Field>::BasePrimeField>>, SynthesisError> {
+                // Prefix the number of points, as for field elements.
+                let mut output = UInt8::constant_vec(&(batch.len() as u64).to_le_bytes());
+                for point in batch {
+                    output.append(&mut point.to_sponge_bytes()?);
+                }
+                Ok(output)
+            }
+
+            fn batch_to_sponge_field_elements(
+                batch: &[Self],
+            ) -> Result<Vec<FpVar<<P::BaseField as Field>::BasePrimeField>>, SynthesisError> {
+                // Prefix the number of points so that a batch cannot collide with a differently
+                // segmented absorb. The length is statically known, so it is a constant.
+                let mut output = vec![FpVar::constant((batch.len() as u64).into())];
+                for point in batch {
+                    output.append(&mut point.to_constraint_field()?);
+                }
+                Ok(output)
+            }
+
+            fn batch_to_sponge_bytes_with_length(
+                batch: &[Self],
+            ) -> Result<Vec<UInt8<<P::BaseField as Field>::BasePrimeField>>, SynthesisError> {
+                Self::batch_to_sponge_bytes(batch)
+            }
+
+            fn batch_to_sponge_field_elements_with_length(
+                batch: &[Self],
+            ) -> Result<Vec<FpVar<<P::BaseField as Field>::BasePrimeField>>, SynthesisError> {
+                Self::batch_to_sponge_field_elements(batch)
+            }
         }
     };
 }
@@ -143,6 +272,326 @@ where
     }
 }
 
+impl<BF, P> AbsorbGadget<P::BasePrimeField> for QuadExtVar<BF, P>
+where
+    BF: FieldVar<P::BaseField, P::BasePrimeField> + AbsorbGadget<P::BasePrimeField>,
+    for<'a> &'a BF: FieldOpsBounds<'a, P::BaseField, BF>,
+    P: QuadExtVarParams<BF>,
+{
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<P::BasePrimeField>>, SynthesisError> {
+        let mut output = self.c0.to_sponge_bytes()?;
+        output.append(&mut self.c1.to_sponge_bytes()?);
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<P::BasePrimeField>>, SynthesisError> {
+        let mut output = self.c0.to_sponge_field_elements()?;
+        output.append(&mut self.c1.to_sponge_field_elements()?);
+        Ok(output)
+    }
+}
+
+impl<BF, P> AbsorbGadget<P::BasePrimeField> for CubicExtVar<BF, P>
+where
+    BF: FieldVar<P::BaseField, P::BasePrimeField> + AbsorbGadget<P::BasePrimeField>,
+    for<'a> &'a BF: FieldOpsBounds<'a, P::BaseField, BF>,
+    P: CubicExtVarParams<BF>,
+{
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<P::BasePrimeField>>, SynthesisError> {
+        let mut output = self.c0.to_sponge_bytes()?;
+        output.append(&mut self.c1.to_sponge_bytes()?);
+        output.append(&mut self.c2.to_sponge_bytes()?);
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<P::BasePrimeField>>, SynthesisError> {
+        let mut output = self.c0.to_sponge_field_elements()?;
+        output.append(&mut self.c1.to_sponge_field_elements()?);
+        output.append(&mut self.c2.to_sponge_field_elements()?);
+        Ok(output)
+    }
+}
+
+/// Returns the bits `[parity(y), infinity]` that make up the flag of a compressed point.
+fn sw_compression_flags<P: SWModelParameters>(
+    point: &SWAffineVar<P, FpVar<P::BaseField>>,
+) -> Result<[Boolean<P::BaseField>; 2], SynthesisError>
+where
+    P::BaseField: PrimeField,
+{
+    let y_parity = point.y.to_bits_le()?[0].clone();
+    Ok([y_parity, point.infinity.clone()])
+}
+
+impl<P> AbsorbGadget<P::BaseField> for Compressed<SWAffineVar<P, FpVar<P::BaseField>>>
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<P::BaseField>>, SynthesisError> {
+        let mut flag_bits = sw_compression_flags(&self.0)?.to_vec();
+        flag_bits.resize(8, Boolean::FALSE);
+
+        let mut output = self.0.x.to_bytes()?;
+        output.push(UInt8::from_bits_le(&flag_bits));
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<P::BaseField>>, SynthesisError> {
+        let flags = Boolean::le_bits_to_fp_var(&sw_compression_flags(&self.0)?)?;
+        Ok(vec![self.0.x.clone(), flags])
+    }
+}
+
+impl<P> Montgomery<TEAffineVar<P, FpVar<P::BaseField>>>
+where
+    P: TEModelParameters,
+    P::BaseField: PrimeField,
+{
+    /// Returns the Montgomery coordinates `(u, v)` of the point, with the same encoding of the
+    /// identity and of the point of order two as the native `Montgomery::coordinates`.
+    pub fn coordinates(&self) -> Result<[FpVar<P::BaseField>; 2], SynthesisError> {
+        let one = FpVar::one();
+        let TEAffineVar { x, y, .. } = &self.0;
+
+        // On the curve, `x = 0` exactly at the identity and at the point of order two, which are
+        // the only points where the denominators vanish. Replace them by one there so that the
+        // inverses always exist, then select the encoding of those points: `u = 0` and
+        // `v = (1 + y) / 2`, which is 1 at the identity and 0 at the point of order two.
+        let x_is_zero = x.is_zero()?;
+        let one_minus_y = x_is_zero.select(&one, &(&one - y))?;
+        let x_or_one = x_is_zero.select(&one, x)?;
+
+        let u = (&one + y) * one_minus_y.inverse()?;
+        let v = &u * x_or_one.inverse()?;
+
+        let half = P::BaseField::from(2u64).inverse().unwrap();
+        let u = x_is_zero.select(&FpVar::zero(), &u)?;
+        let v = x_is_zero.select(&((&one + y) * half), &v)?;
+        Ok([u, v])
+    }
+}
+
+impl<P> AbsorbGadget<P::BaseField> for Montgomery<TEAffineVar<P, FpVar<P::BaseField>>>
+where
+    P: TEModelParameters,
+    P::BaseField: PrimeField,
+{
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<P::BaseField>>, SynthesisError> {
+        let [u, v] = self.coordinates()?;
+        let mut output = u.to_bytes()?;
+        output.append(&mut v.to_bytes()?);
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<P::BaseField>>, SynthesisError> {
+        let [u, v] = self.coordinates()?;
+        Ok(vec![u, v])
+    }
+}
+
+/// Returns the flag and the coordinates of a point absorbed by `WithInfinityFlag`, with the
+/// coordinates zeroed out at infinity.
+fn flagged_coordinates_var<F: PrimeField>(
+    is_infinity: Boolean<F>,
+    x: &FpVar<F>,
+    y: &FpVar<F>,
+) -> Result<(Boolean<F>, [FpVar<F>; 2]), SynthesisError> {
+    let x = is_infinity.select(&FpVar::zero(), x)?;
+    let y = is_infinity.select(&FpVar::zero(), y)?;
+    Ok((is_infinity, [x, y]))
+}
+
+fn flagged_to_sponge_bytes_var<F: PrimeField>(
+    is_infinity: Boolean<F>,
+    x: &FpVar<F>,
+    y: &FpVar<F>,
+) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    let (flag, [x, y]) = flagged_coordinates_var(is_infinity, x, y)?;
+    let mut flag_bits = vec![flag];
+    flag_bits.resize(8, Boolean::FALSE);
+
+    let mut output = vec![UInt8::from_bits_le(&flag_bits)];
+    output.append(&mut x.to_bytes()?);
+    output.append(&mut y.to_bytes()?);
+    Ok(output)
+}
+
+fn flagged_to_sponge_field_elements_var<F: PrimeField>(
+    is_infinity: Boolean<F>,
+    x: &FpVar<F>,
+    y: &FpVar<F>,
+) -> Result<Vec<FpVar<F>>, SynthesisError> {
+    let (flag, [x, y]) = flagged_coordinates_var(is_infinity, x, y)?;
+    Ok(vec![flag.into(), x, y])
+}
+
+impl<P> AbsorbGadget<P::BaseField> for WithInfinityFlag<SWAffineVar<P, FpVar<P::BaseField>>>
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<P::BaseField>>, SynthesisError> {
+        flagged_to_sponge_bytes_var(self.0.infinity.clone(), &self.0.x, &self.0.y)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<P::BaseField>>, SynthesisError> {
+        flagged_to_sponge_field_elements_var(self.0.infinity.clone(), &self.0.x, &self.0.y)
+    }
+}
+
+/// The identity `(0, 1)` is the only point with `x = 0` and `y = 1`, so the flag costs two
+/// equality checks.
+impl<P> AbsorbGadget<P::BaseField> for WithInfinityFlag<TEAffineVar<P, FpVar<P::BaseField>>>
+where
+    P: TEModelParameters,
+    P::BaseField: PrimeField,
+{
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<P::BaseField>>, SynthesisError> {
+        let is_identity = self.0.x.is_zero()?.and(&self.0.y.is_one()?)?;
+        flagged_to_sponge_bytes_var(is_identity, &self.0.x, &self.0.y)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<P::BaseField>>, SynthesisError> {
+        let is_identity = self.0.x.is_zero()?.and(&self.0.y.is_one()?)?;
+        flagged_to_sponge_field_elements_var(is_identity, &self.0.x, &self.0.y)
+    }
+}
+
+/// The limbs are linear combinations of the bits, which are booleans, so they are range
+/// checked without extra constraints. The number of limbs is a constant.
+impl<F: PrimeField> AbsorbGadget<F> for Limbs<Vec<Boolean<F>>> {
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let limbs = self.bits.chunks(self.limb_bits);
+        let mut output = UInt8::constant_vec(&(limbs.len() as u64).to_le_bytes());
+        for limb in limbs {
+            let mut limb = limb.to_vec();
+            limb.resize((self.limb_bits + 7) / 8 * 8, Boolean::FALSE);
+            output.extend(limb.chunks(8).map(UInt8::from_bits_le));
+        }
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        assert!(
+            self.limb_bits <= F::Params::CAPACITY as usize,
+            "limbs of {} bits do not fit in a single field element",
+            self.limb_bits
+        );
+        let limbs = self.bits.chunks(self.limb_bits);
+        let mut output = vec![FpVar::constant(F::from(limbs.len() as u64))];
+        for limb in limbs {
+            output.push(Boolean::le_bits_to_fp_var(limb)?);
+        }
+        Ok(output)
+    }
+}
+
+/// Absorbs like the native `Message`. The length of the message is statically known, so it is
+/// a constant.
+impl<F: PrimeField, B: AsRef<[UInt8<F>]>> AbsorbGadget<F> for Message<B> {
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let message = self.0.as_ref();
+        let mut output = UInt8::constant_vec(&(message.len() as u64).to_le_bytes());
+        output.extend_from_slice(message);
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let message = self.0.as_ref();
+        let block_len = (F::Params::CAPACITY / 8) as usize;
+        let mut output = vec![FpVar::Constant(F::from(message.len() as u64))];
+        for block in message.chunks(block_len) {
+            output.push(Boolean::le_bits_to_fp_var(&block.to_bits_le()?)?);
+        }
+        Ok(output)
+    }
+}
+
+/// Big-endian bytes reverse the little-endian ones, so the byte order costs no constraints.
+impl<F: PrimeField> AbsorbGadget<F> for WithEndianness<FpVar<F>> {
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let mut bytes = self.0.to_bytes()?;
+        if self.1 == Endianness::Big {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(vec![self.0.clone()])
+    }
+}
+
+/// Absorbs like the native `DensePolynomial`: the number of coefficients, as a constant,
+/// followed by the coefficients from the constant term up.
+#[cfg(feature = "ark-poly")]
+impl<F: PrimeField> AbsorbGadget<F> for DensePolynomialVar<F> {
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let mut output = UInt8::constant_vec(&(self.coeffs.len() as u64).to_le_bytes());
+        for coeff in &self.coeffs {
+            output.append(&mut coeff.to_bytes()?);
+        }
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut output = vec![FpVar::Constant(F::from(self.coeffs.len() as u64))];
+        output.extend_from_slice(&self.coeffs);
+        Ok(output)
+    }
+}
+
+/// Absorbs like the native `Proof`: the elements `A`, `B` and `C`, in that order.
+#[cfg(feature = "snark")]
+impl<F, E, P> AbsorbGadget<F> for ProofVar<E, P>
+where
+    F: PrimeField,
+    E: PairingEngine,
+    P: PairingVar<E>,
+    P::G1Var: AbsorbGadget<F>,
+    P::G2Var: AbsorbGadget<F>,
+{
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let mut output = self.a.to_sponge_bytes()?;
+        output.append(&mut self.b.to_sponge_bytes()?);
+        output.append(&mut self.c.to_sponge_bytes()?);
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut output = self.a.to_sponge_field_elements()?;
+        output.append(&mut self.b.to_sponge_field_elements()?);
+        output.append(&mut self.c.to_sponge_field_elements()?);
+        Ok(output)
+    }
+}
+
+/// The unit type carries no data and absorbs nothing.
+impl<F: PrimeField> AbsorbGadget<F> for () {
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        Ok(Vec::new())
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(Vec::new())
+    }
+}
+
+/// A `PhantomData` marker carries no data and absorbs nothing, so that structs with marker
+/// fields can derive `AbsorbGadget`.
+impl<F: PrimeField, T: ?Sized> AbsorbGadget<F> for PhantomData<T> {
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        Ok(Vec::new())
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(Vec::new())
+    }
+}
+
+/// Absorbs like the native `&[A]`: a list of lists absorbs its number of lists, then the length
+/// and the contents of each inner list. The lengths are constants.
 impl<F: PrimeField, A: AbsorbGadget<F>> AbsorbGadget<F> for &[A] {
     fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
         A::batch_to_sponge_bytes(self)
@@ -151,8 +600,39 @@ impl<F: PrimeField, A: AbsorbGadget<F>> AbsorbGadget<F> for &[A] {
     fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
         A::batch_to_sponge_field_elements(self)
     }
+
+    fn to_sponge_field_elements_in(&self, dest: &mut Vec<FpVar<F>>) -> Result<(), SynthesisError> {
+        A::batch_to_sponge_field_elements_in(self, dest)
+    }
+
+    fn batch_to_sponge_bytes(batch: &[Self]) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let mut output = UInt8::constant_vec(&(batch.len() as u64).to_le_bytes());
+        for item in batch {
+            output.append(&mut A::batch_to_sponge_bytes_with_length(item)?);
+        }
+        Ok(output)
+    }
+
+    fn batch_to_sponge_field_elements(batch: &[Self]) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut output = vec![FpVar::Constant(F::from(batch.len() as u64))];
+        for item in batch {
+            output.append(&mut A::batch_to_sponge_field_elements_with_length(item)?);
+        }
+        Ok(output)
+    }
+
+    fn batch_to_sponge_bytes_with_length(batch: &[Self]) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        Self::batch_to_sponge_bytes(batch)
+    }
+
+    fn batch_to_sponge_field_elements_with_length(
+        batch: &[Self],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Self::batch_to_sponge_field_elements(batch)
+    }
 }
 
+/// Absorbs like a `&[A]`, including when nested in a list.
 impl<F: PrimeField, A: AbsorbGadget<F>> AbsorbGadget<F> for Vec<A> {
     fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
         self.as_slice().to_sponge_bytes()
@@ -161,6 +641,51 @@ impl<F: PrimeField, A: AbsorbGadget<F>> AbsorbGadget<F> for Vec<A> {
     fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
         self.as_slice().to_sponge_field_elements()
     }
+
+    fn to_sponge_field_elements_in(&self, dest: &mut Vec<FpVar<F>>) -> Result<(), SynthesisError> {
+        self.as_slice().to_sponge_field_elements_in(dest)
+    }
+
+    fn batch_to_sponge_bytes(batch: &[Self]) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let mut output = UInt8::constant_vec(&(batch.len() as u64).to_le_bytes());
+        for item in batch {
+            output.append(&mut A::batch_to_sponge_bytes_with_length(item)?);
+        }
+        Ok(output)
+    }
+
+    fn batch_to_sponge_field_elements(batch: &[Self]) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut output = vec![FpVar::Constant(F::from(batch.len() as u64))];
+        for item in batch {
+            output.append(&mut A::batch_to_sponge_field_elements_with_length(item)?);
+        }
+        Ok(output)
+    }
+
+    fn batch_to_sponge_bytes_with_length(batch: &[Self]) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        Self::batch_to_sponge_bytes(batch)
+    }
+
+    fn batch_to_sponge_field_elements_with_length(
+        batch: &[Self],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Self::batch_to_sponge_field_elements(batch)
+    }
+}
+
+/// Arrays absorb like slices, so `[FpVar<F>; N]` absorbs exactly its `N` elements.
+impl<F: PrimeField, A: AbsorbGadget<F>, const N: usize> AbsorbGadget<F> for [A; N] {
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        self.as_ref().to_sponge_bytes()
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        self.as_ref().to_sponge_field_elements()
+    }
+
+    fn to_sponge_field_elements_in(&self, dest: &mut Vec<FpVar<F>>) -> Result<(), SynthesisError> {
+        self.as_ref().to_sponge_field_elements_in(dest)
+    }
 }
 
 impl<F: PrimeField, A: AbsorbGadget<F>> AbsorbGadget<F> for Option<A> {
@@ -182,6 +707,20 @@ impl<F: PrimeField, A: AbsorbGadget<F>> AbsorbGadget<F> for Option<A> {
     }
 }
 
+impl<F: PrimeField, A: AbsorbGadget<F>, B: AbsorbGadget<F>> AbsorbGadget<F> for (A, B) {
+    fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let mut output = self.0.to_sponge_bytes()?;
+        output.append(&mut self.1.to_sponge_bytes()?);
+        Ok(output)
+    }
+
+    fn to_sponge_field_elements(&self) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut output = self.0.to_sponge_field_elements()?;
+        output.append(&mut self.1.to_sponge_field_elements()?);
+        Ok(output)
+    }
+}
+
 impl<F: PrimeField, A: AbsorbGadget<F>> AbsorbGadget<F> for &A {
     fn to_sponge_bytes(&self) -> Result<Vec<UInt8<F>>, SynthesisError> {
         (*self).to_sponge_bytes()
@@ -192,6 +731,20 @@ impl<F: PrimeField, A: AbsorbGadget<F>> AbsorbGadget<F> for &A {
     }
 }
 
+/// Converts a list of different [`AbsorbGadget`]s into sponge field elements, in order.
+///
+/// This is the function counterpart of [`collect_sponge_field_elements_gadget!`], for contexts
+/// where the `?` inside the macro is inconvenient.
+pub fn collect_field_elements<F: PrimeField>(
+    items: &[&dyn AbsorbGadget<F>],
+) -> Result<Vec<FpVar<F>>, SynthesisError> {
+    let mut output = Vec::new();
+    for item in items {
+        output.append(&mut item.to_sponge_field_elements()?);
+    }
+    Ok(output)
+}
+
 /// Individually absorbs each element in a comma-separated list of [`Absorbable`]s into a sponge.
 /// Format is `absorb!(s, a_0, a_1, ..., a_n)`, where `s` is a mutable reference to a sponge
 /// and each `a_i` implements `AbsorbableVar`.
@@ -221,16 +774,33 @@ macro_rules! collect_sponge_field_elements_gadget {
 
 #[cfg(test)]
 mod tests {
-    use crate::constraints::AbsorbGadget;
-    use crate::Absorb;
-    use ark_r1cs_std::alloc::AllocVar;
+    use crate::absorb::tests::{jubjub_test_points, Fq2, Fq2Parameters, JubjubParameters};
+    use crate::constraints::{collect_field_elements, AbsorbGadget, CryptographicSpongeVar};
+    use crate::poseidon::constraints::PoseidonSpongeVar;
+    use crate::poseidon::tests::poseidon_parameters_for_test;
+    use crate::poseidon::PoseidonSponge;
+    use crate::{
+        Absorb, Compressed, CryptographicSponge, Endianness, FieldBasedCryptographicSponge, Limbs,
+        Message, Montgomery, WithEndianness, WithInfinityFlag,
+    };
+    use ark_ec::short_weierstrass_jacobian::GroupAffine as SWAffine;
+    use ark_ec::{AffineCurve, ProjectiveCurve};
+    use ark_ff::{BigInteger, PrimeField, Zero};
+    use ark_r1cs_std::alloc::{AllocVar, AllocationMode};
+    use ark_r1cs_std::bits::boolean::Boolean;
     use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::fields::fp2::Fp2Var;
+    use ark_r1cs_std::groups::curves::short_weierstrass::ProjectiveVar;
+    use ark_r1cs_std::groups::curves::twisted_edwards::{
+        AffineVar as TEAffineVar, MontgomeryAffineVar,
+    };
+    use ark_r1cs_std::groups::CurveVar;
     use ark_r1cs_std::uint8::UInt8;
     use ark_r1cs_std::R1CSVar;
-    use ark_relations::r1cs::ConstraintSystem;
+    use ark_relations::r1cs::{ConstraintSystem, SynthesisError};
     use ark_relations::*;
     use ark_std::{test_rng, UniformRand};
-    use ark_test_curves::bls12_381::Fr;
+    use ark_test_curves::bls12_381::{g1::Parameters as G1Parameters, Fq, Fr, G1Projective};
 
     #[test]
     fn consistency_check() {
@@ -260,4 +830,601 @@ mod tests {
 
         assert!(cs.is_satisfied().unwrap())
     }
+
+    #[test]
+    fn slice_consistency() {
+        // Slices and vectors go through the batch conversions of their elements on both sides.
+        fn check<N: Absorb + Clone, G: AbsorbGadget<Fr> + Clone>(native: &[N], gadget: &[G]) {
+            assert_eq!(
+                gadget.to_sponge_field_elements().unwrap().value().unwrap(),
+                native.to_sponge_field_elements_as_vec::<Fr>()
+            );
+            assert_eq!(
+                gadget.to_sponge_bytes().unwrap().value().unwrap(),
+                native.to_sponge_bytes_as_vec()
+            );
+            assert_eq!(
+                gadget
+                    .to_vec()
+                    .to_sponge_field_elements()
+                    .unwrap()
+                    .value()
+                    .unwrap(),
+                native.to_vec().to_sponge_field_elements_as_vec::<Fr>()
+            );
+        }
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut rng = test_rng();
+
+        let bytes: Vec<u8> = (0..40).collect();
+        let bytes_var = UInt8::new_witness_vec(ns!(cs, "bytes"), &bytes).unwrap();
+        let bits: Vec<bool> = (0..10).map(|i| i % 3 == 0).collect();
+        let bits_var: Vec<_> = bits
+            .iter()
+            .map(|bit| Boolean::new_witness(ns!(cs, "bit"), || Ok(*bit)).unwrap())
+            .collect();
+        let elems: Vec<Fr> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+        let elems_var: Vec<_> = elems
+            .iter()
+            .map(|elem| FpVar::new_witness(ns!(cs, "elem"), || Ok(*elem)).unwrap())
+            .collect();
+        let options = vec![Some(elems[0]), None, Some(elems[1])];
+        let options_var = vec![Some(elems_var[0].clone()), None, Some(elems_var[1].clone())];
+
+        for len in [0, 1, 5] {
+            check(&bytes[..len], &bytes_var[..len]);
+            check(&bits[..len], &bits_var[..len]);
+            check(&elems[..len], &elems_var[..len]);
+        }
+        check(&bytes, &bytes_var);
+        check(&bits, &bits_var);
+        check(&options, &options_var);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn compressed_sw_point() {
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let mut rng = test_rng();
+
+        let point = G1Projective::rand(&mut rng);
+        for point in [point, -point, G1Projective::zero()] {
+            let point_var =
+                ProjectiveVar::<G1Parameters, FpVar<Fq>>::new_witness(ns!(cs, "point"), || {
+                    Ok(point)
+                })
+                .unwrap()
+                .to_affine()
+                .unwrap();
+            let compressed = Compressed(point.into_affine());
+            let compressed_var = Compressed(point_var.clone());
+
+            let native_elems: Vec<Fq> = compressed.to_sponge_field_elements_as_vec();
+            let constraint_elems = compressed_var.to_sponge_field_elements().unwrap();
+            assert_eq!(constraint_elems.value().unwrap(), native_elems);
+
+            let native_bytes = compressed.to_sponge_bytes_as_vec();
+            let constraint_bytes = compressed_var.to_sponge_bytes().unwrap();
+            assert_eq!(constraint_bytes.value().unwrap(), native_bytes);
+
+            // The compressed form keeps `x` and merges the sign of `y` with the infinity flag.
+            let uncompressed_elems = point_var.to_sponge_field_elements().unwrap();
+            assert_eq!(uncompressed_elems.len(), 3);
+            assert_eq!(constraint_elems.len(), 2);
+            assert_eq!(
+                uncompressed_elems[0].value().unwrap(),
+                constraint_elems[0].value().unwrap()
+            );
+        }
+
+        // A point and its negation share an x-coordinate but not a compressed encoding.
+        let point = point.into_affine();
+        assert_ne!(
+            Compressed(point).to_sponge_field_elements_as_vec::<Fq>(),
+            Compressed(-point).to_sponge_field_elements_as_vec::<Fq>()
+        );
+
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn batched_points_are_length_prefixed() {
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let mut rng = test_rng();
+
+        let points: Vec<_> = (0..3).map(|_| G1Projective::rand(&mut rng)).collect();
+        let point_vars: Vec<_> = points
+            .iter()
+            .map(|point| {
+                ProjectiveVar::<G1Parameters, FpVar<Fq>>::new_witness(ns!(cs, "point"), || {
+                    Ok(*point)
+                })
+                .unwrap()
+                .to_affine()
+                .unwrap()
+            })
+            .collect();
+
+        let native_points = G1Projective::batch_normalization_into_affine(&points);
+        let native_elems: Vec<Fq> = native_points.to_sponge_field_elements_as_vec();
+        let constraint_elems = point_vars.to_sponge_field_elements().unwrap();
+        assert_eq!(constraint_elems.value().unwrap(), native_elems);
+        assert_eq!(constraint_elems[0].value().unwrap(), Fq::from(3u64));
+
+        // Bytes get the same prefix, as a little-endian `u64`.
+        let mut native_bytes = 3u64.to_le_bytes().to_vec();
+        let mut constraint_bytes = native_bytes.clone();
+        for (point, point_var) in native_points.iter().zip(&point_vars) {
+            native_bytes.append(&mut point.to_sponge_bytes_as_vec());
+            constraint_bytes.append(&mut point_var.to_sponge_bytes().unwrap().value().unwrap());
+        }
+        assert_eq!(native_points.to_sponge_bytes_as_vec(), native_bytes);
+        assert_eq!(
+            point_vars.to_sponge_bytes().unwrap().value().unwrap(),
+            constraint_bytes
+        );
+
+        let params = poseidon_parameters_for_test::<Fq>();
+        let mut batched = PoseidonSpongeVar::new(cs.clone(), &params);
+        batched.absorb(&point_vars).unwrap();
+
+        let mut separate = PoseidonSpongeVar::new(cs.clone(), &params);
+        for point_var in &point_vars {
+            separate.absorb(point_var).unwrap();
+        }
+
+        assert_ne!(
+            batched.squeeze_field_elements(1).unwrap().value().unwrap(),
+            separate.squeeze_field_elements(1).unwrap().value().unwrap()
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn quadratic_extension_consistency() {
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let mut rng = test_rng();
+
+        let elem = Fq2::rand(&mut rng);
+        let elem_var = Fp2Var::<Fq2Parameters>::new_witness(ns!(cs, "elem"), || Ok(elem)).unwrap();
+
+        let native_elems: Vec<Fq> = elem.to_sponge_field_elements_as_vec();
+        let constraint_elems = elem_var.to_sponge_field_elements().unwrap();
+        assert_eq!(constraint_elems.value().unwrap(), native_elems);
+
+        let native_bytes = elem.to_sponge_bytes_as_vec();
+        let constraint_bytes = elem_var.to_sponge_bytes().unwrap();
+        assert_eq!(constraint_bytes.value().unwrap(), native_bytes);
+
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    fn collect_with_macro(
+        a: &FpVar<Fr>,
+        b: &[UInt8<Fr>],
+        c: &Boolean<Fr>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        collect_sponge_field_elements_gadget!(a, b, c)
+    }
+
+    #[test]
+    fn collect_field_elements_matches_macro() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut rng = test_rng();
+
+        let a = FpVar::new_witness(ns!(cs, "a"), || Ok(Fr::rand(&mut rng))).unwrap();
+        let b = UInt8::new_witness_vec(ns!(cs, "b"), &[1, 2, 3]).unwrap();
+        let c = Boolean::new_witness(ns!(cs, "c"), || Ok(true)).unwrap();
+
+        let collected = collect_field_elements(&[&a, &b, &c]).unwrap();
+        let expected = collect_with_macro(&a, &b, &c).unwrap();
+        assert_eq!(collected.value().unwrap(), expected.value().unwrap());
+    }
+
+    #[test]
+    fn montgomery_form_consistency() {
+        for point in jubjub_test_points() {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let point_var =
+                TEAffineVar::<JubjubParameters, FpVar<Fr>>::new_variable_omit_prime_order_check(
+                    ns!(cs, "point"),
+                    || Ok(point.into_projective()),
+                    AllocationMode::Witness,
+                )
+                .unwrap();
+
+            let [u, v] = Montgomery(point).coordinates();
+            assert_eq!(
+                (u, v),
+                MontgomeryAffineVar::<JubjubParameters, FpVar<Fr>>::from_edwards_to_coords(&point)
+                    .unwrap()
+            );
+
+            let wrapped = Montgomery(point_var);
+            assert_eq!(
+                wrapped.to_sponge_field_elements().unwrap().value().unwrap(),
+                vec![u, v]
+            );
+            assert_eq!(
+                wrapped.to_sponge_bytes().unwrap().value().unwrap(),
+                Montgomery(point).to_sponge_bytes_as_vec()
+            );
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn limbs_consistency() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut rng = test_rng();
+
+        let bits: Vec<bool> = (0..512).map(|_| bool::rand(&mut rng)).collect();
+        let bits_var: Vec<_> = bits
+            .iter()
+            .map(|bit| Boolean::new_witness(ns!(cs, "bit"), || Ok(*bit)).unwrap())
+            .collect();
+
+        for len in [512, 256, 100] {
+            let native = Limbs::new(bits[..len].to_vec(), 64);
+            let gadget = Limbs::new(bits_var[..len].to_vec(), 64);
+            assert_eq!(
+                gadget.to_sponge_field_elements().unwrap().value().unwrap(),
+                native.to_sponge_field_elements_as_vec::<Fr>()
+            );
+            assert_eq!(
+                gadget.to_sponge_bytes().unwrap().value().unwrap(),
+                native.to_sponge_bytes_as_vec()
+            );
+        }
+
+        // The low half of a 512-bit integer has the same low limbs but absorbs differently.
+        let sponge_params = poseidon_parameters_for_test();
+        let squeeze = |limbs: &Limbs<Vec<Boolean<Fr>>>| {
+            let mut sponge = PoseidonSpongeVar::new(cs.clone(), &sponge_params);
+            sponge.absorb(limbs).unwrap();
+            sponge.squeeze_field_elements(1).unwrap().value().unwrap()
+        };
+        let wide = Limbs::new(bits_var.clone(), 64);
+        let narrow = Limbs::new(bits_var[..256].to_vec(), 64);
+        assert_ne!(squeeze(&wide), squeeze(&narrow));
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn infinity_flag_consistency() {
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let mut rng = test_rng();
+
+        // `(0, 2)` is on the curve `y^2 = x^3 + 4`, but not in the prime order subgroup.
+        let zero_x = SWAffine::<G1Parameters>::new(Fq::zero(), Fq::from(2u64), false);
+        let mut sw_encodings = Vec::new();
+        for point in [
+            G1Projective::rand(&mut rng).into_affine(),
+            SWAffine::zero(),
+            zero_x,
+        ] {
+            let z = if point.infinity {
+                Fq::zero()
+            } else {
+                Fq::from(1u64)
+            };
+            let point_var = ProjectiveVar::<G1Parameters, FpVar<Fq>>::new(
+                FpVar::new_witness(ns!(cs, "x"), || Ok(point.x)).unwrap(),
+                FpVar::new_witness(ns!(cs, "y"), || Ok(point.y)).unwrap(),
+                FpVar::new_witness(ns!(cs, "z"), || Ok(z)).unwrap(),
+            )
+            .to_affine()
+            .unwrap();
+            let native_elems: Vec<Fq> = WithInfinityFlag(point).to_sponge_field_elements_as_vec();
+            let constraint_elems = WithInfinityFlag(point_var.clone())
+                .to_sponge_field_elements()
+                .unwrap();
+            assert_eq!(constraint_elems.value().unwrap(), native_elems);
+            assert_eq!(
+                WithInfinityFlag(point_var)
+                    .to_sponge_bytes()
+                    .unwrap()
+                    .value()
+                    .unwrap(),
+                WithInfinityFlag(point).to_sponge_bytes_as_vec()
+            );
+            sw_encodings.push(native_elems);
+        }
+        assert_eq!(
+            sw_encodings[1],
+            vec![Fq::from(1u64), Fq::zero(), Fq::zero()]
+        );
+        assert_ne!(sw_encodings[1], sw_encodings[2]);
+        assert!(cs.is_satisfied().unwrap());
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut te_encodings = Vec::new();
+        for point in jubjub_test_points() {
+            let point_var =
+                TEAffineVar::<JubjubParameters, FpVar<Fr>>::new_variable_omit_prime_order_check(
+                    ns!(cs, "point"),
+                    || Ok(point.into_projective()),
+                    AllocationMode::Witness,
+                )
+                .unwrap();
+            let native_elems: Vec<Fr> = WithInfinityFlag(point).to_sponge_field_elements_as_vec();
+            let constraint_elems = WithInfinityFlag(point_var.clone())
+                .to_sponge_field_elements()
+                .unwrap();
+            assert_eq!(constraint_elems.value().unwrap(), native_elems);
+            assert_eq!(
+                WithInfinityFlag(point_var)
+                    .to_sponge_bytes()
+                    .unwrap()
+                    .value()
+                    .unwrap(),
+                WithInfinityFlag(point).to_sponge_bytes_as_vec()
+            );
+            te_encodings.push(native_elems);
+        }
+        // The identity and the point of order two both have `x = 0`.
+        assert_eq!(te_encodings[1][0], Fr::from(1u64));
+        assert_eq!(te_encodings[2][0], Fr::zero());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn message_consistency() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let message: Vec<u8> = (0..70).collect();
+        let message_var = UInt8::new_witness_vec(ns!(cs, "message"), &message).unwrap();
+
+        for len in [0, 3, 31, 62, 70] {
+            let native = Message(&message[..len]);
+            let gadget = Message(&message_var[..len]);
+            let elements = gadget.to_sponge_field_elements().unwrap();
+            assert_eq!(elements.len(), 1 + (len + 30) / 31);
+            assert_eq!(
+                elements.value().unwrap(),
+                native.to_sponge_field_elements_as_vec::<Fr>()
+            );
+            assert_eq!(
+                gadget.to_sponge_bytes().unwrap().value().unwrap(),
+                native.to_sponge_bytes_as_vec()
+            );
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn nested_vec_consistency() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut rng = test_rng();
+        let [a, b, c] = [Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng)];
+        let var = |elem| FpVar::new_witness(ns!(cs, "elem"), || Ok(elem)).unwrap();
+
+        let left = vec![vec![a], vec![b, c]];
+        let right = vec![vec![a, b], vec![c]];
+        let len = |n: u64| Fr::from(n);
+        assert_eq!(
+            left.to_sponge_field_elements_as_vec::<Fr>(),
+            vec![len(2), len(1), a, len(2), b, c]
+        );
+        // Without the number of lists, `[[a]]` followed by `[1, b]` would absorb the same
+        // elements as `[[a], [b]]`.
+        let mut split = vec![vec![a]].to_sponge_field_elements_as_vec::<Fr>();
+        split.append(&mut vec![len(1), b].to_sponge_field_elements_as_vec::<Fr>());
+        assert_ne!(
+            split,
+            vec![vec![a], vec![b]].to_sponge_field_elements_as_vec::<Fr>()
+        );
+        // Each level absorbs its number of lists once.
+        assert_eq!(
+            vec![vec![vec![a]]].to_sponge_field_elements_as_vec::<Fr>(),
+            vec![len(1), len(1), len(1), a]
+        );
+        assert_ne!(
+            left.to_sponge_field_elements_as_vec::<Fr>(),
+            right.to_sponge_field_elements_as_vec::<Fr>()
+        );
+        assert_ne!(
+            left.to_sponge_bytes_as_vec(),
+            right.to_sponge_bytes_as_vec()
+        );
+
+        for native in [left, right] {
+            let gadget: Vec<Vec<_>> = native
+                .iter()
+                .map(|row| row.iter().map(|elem| var(*elem)).collect())
+                .collect();
+            assert_eq!(
+                gadget.to_sponge_field_elements().unwrap().value().unwrap(),
+                native.to_sponge_field_elements_as_vec::<Fr>()
+            );
+            assert_eq!(
+                gadget.to_sponge_bytes().unwrap().value().unwrap(),
+                native.to_sponge_bytes_as_vec()
+            );
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn nested_bytes_absorb_like_slices() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let rows: Vec<Vec<u8>> = vec![vec![1, 2, 3], vec![], (0..40).collect()];
+        let slices: Vec<&[u8]> = rows.iter().map(|row| row.as_slice()).collect();
+
+        // The number of rows comes first, and each row is length-prefixed once, by its own
+        // encoding as a batch of bytes.
+        let mut elements = vec![Fr::from(rows.len() as u64)];
+        let mut bytes = (rows.len() as u64).to_le_bytes().to_vec();
+        for row in &rows {
+            elements.append(&mut row.to_sponge_field_elements_as_vec::<Fr>());
+            bytes.extend_from_slice(&(row.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(row);
+        }
+        assert_eq!(rows.to_sponge_field_elements_as_vec::<Fr>(), elements);
+        assert_eq!(
+            slices.as_slice().to_sponge_field_elements_as_vec::<Fr>(),
+            elements
+        );
+        assert_eq!(rows.to_sponge_bytes_as_vec(), bytes);
+        assert_eq!(slices.as_slice().to_sponge_bytes_as_vec(), bytes);
+
+        let row_vars: Vec<Vec<UInt8<Fr>>> = rows
+            .iter()
+            .map(|row| UInt8::new_witness_vec(ns!(cs, "row"), row).unwrap())
+            .collect();
+        let slice_vars: Vec<&[UInt8<Fr>]> = row_vars.iter().map(|row| row.as_slice()).collect();
+        for gadget in [
+            row_vars.to_sponge_field_elements().unwrap(),
+            slice_vars.as_slice().to_sponge_field_elements().unwrap(),
+        ] {
+            assert_eq!(gadget.value().unwrap(), elements);
+        }
+        for gadget in [
+            row_vars.to_sponge_bytes().unwrap(),
+            slice_vars.as_slice().to_sponge_bytes().unwrap(),
+        ] {
+            assert_eq!(gadget.value().unwrap(), bytes);
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn array_consistency() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut rng = test_rng();
+        let elems = [Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng)];
+        let [a, b, c] = elems.map(|elem| FpVar::new_witness(ns!(cs, "elem"), || Ok(elem)).unwrap());
+
+        let array = [a.clone(), b.clone(), c.clone()];
+        let elements = array.to_sponge_field_elements().unwrap();
+        assert_eq!(elements.value().unwrap(), [a, b, c].value().unwrap());
+        assert_eq!(
+            elements.value().unwrap(),
+            elems.to_sponge_field_elements_as_vec()
+        );
+        assert_eq!(
+            array.to_sponge_bytes().unwrap().value().unwrap(),
+            elems.to_sponge_bytes_as_vec()
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn big_endian_consistency() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut rng = test_rng();
+        let sponge_params = poseidon_parameters_for_test();
+        let elem = Fr::rand(&mut rng);
+        let elem_var = FpVar::new_witness(ns!(cs, "elem"), || Ok(elem)).unwrap();
+
+        // An off-circuit hasher that serializes field elements big-endian.
+        let reference = elem.into_repr().to_bytes_be();
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&reference);
+
+        let bytes = WithEndianness(elem_var.clone(), Endianness::Big)
+            .to_sponge_bytes()
+            .unwrap();
+        assert_eq!(bytes.value().unwrap(), reference);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        constraint_sponge.absorb(&bytes).unwrap();
+        assert_eq!(
+            constraint_sponge
+                .squeeze_field_elements(1)
+                .unwrap()
+                .value()
+                .unwrap(),
+            native_sponge.squeeze_native_field_elements(1)
+        );
+
+        assert_eq!(
+            WithEndianness(elem, Endianness::Little).to_sponge_bytes_as_vec(),
+            elem.to_sponge_bytes_as_vec()
+        );
+
+        // Field elements are absorbed as they are, whatever the byte order.
+        for endianness in [Endianness::Little, Endianness::Big] {
+            let native = WithEndianness(elem, endianness);
+            let gadget = WithEndianness(elem_var.clone(), endianness);
+            assert_eq!(
+                gadget.to_sponge_bytes().unwrap().value().unwrap(),
+                native.to_sponge_bytes_as_vec()
+            );
+            assert_eq!(
+                gadget.to_sponge_field_elements().unwrap().value().unwrap(),
+                vec![elem]
+            );
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[cfg(feature = "ark-poly")]
+    #[test]
+    fn dense_polynomial_consistency() {
+        use ark_poly::univariate::DensePolynomial;
+        use ark_r1cs_std::poly::polynomial::univariate::dense::DensePolynomialVar;
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut rng = test_rng();
+        let coeffs: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let coeffs_var = Vec::new_witness(ns!(cs, "coeffs"), || Ok(coeffs.clone())).unwrap();
+
+        let native = DensePolynomial { coeffs };
+        let gadget = DensePolynomialVar::from_coefficients_vec(coeffs_var);
+        assert_eq!(
+            gadget.to_sponge_field_elements().unwrap().value().unwrap(),
+            native.to_sponge_field_elements_as_vec::<Fr>()
+        );
+        assert_eq!(
+            gadget.to_sponge_bytes().unwrap().value().unwrap(),
+            native.to_sponge_bytes_as_vec()
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[cfg(feature = "snark")]
+    #[test]
+    fn groth16_proof_consistency() {
+        use ark_bls12_377::constraints::PairingVar;
+        use ark_bls12_377::{Bls12_377, Fq, Fr, G1Affine, G2Affine};
+        use ark_groth16::constraints::ProofVar;
+        use ark_groth16::Proof;
+
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let mut rng = test_rng();
+        let g1 = G1Affine::prime_subgroup_generator();
+        let g2 = G2Affine::prime_subgroup_generator();
+        let proof = Proof::<Bls12_377> {
+            a: g1.mul(Fr::rand(&mut rng)).into_affine(),
+            b: g2.mul(Fr::rand(&mut rng)).into_affine(),
+            c: g1.mul(Fr::rand(&mut rng)).into_affine(),
+        };
+        let proof_var =
+            ProofVar::<Bls12_377, PairingVar>::new_witness(ns!(cs, "proof"), || Ok(proof.clone()))
+                .unwrap();
+
+        assert_eq!(
+            proof_var
+                .to_sponge_field_elements()
+                .unwrap()
+                .value()
+                .unwrap(),
+            proof.to_sponge_field_elements_as_vec::<Fq>()
+        );
+
+        let sponge_params = poseidon_parameters_for_test();
+        let mut native_sponge = PoseidonSponge::<Fq>::new(&sponge_params);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fq>::new(cs.clone(), &sponge_params);
+        native_sponge.absorb(&proof);
+        constraint_sponge.absorb(&proof_var).unwrap();
+        assert_eq!(
+            constraint_sponge
+                .squeeze_field_elements(2)
+                .unwrap()
+                .value()
+                .unwrap(),
+            native_sponge.squeeze_native_field_elements(2)
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
 }
@@ -1,20 +1,37 @@
-use crate::{Absorb, CryptographicSponge, FieldElementSize};
-use ark_ff::PrimeField;
+use crate::{Absorb, CryptographicSponge, Endianness, FieldElementSize, Message};
+use ark_ff::{BigInteger, FpParameters, PrimeField};
 use ark_nonnative_field::params::{get_params, OptimizationType};
 use ark_nonnative_field::{AllocatedNonNativeFieldVar, NonNativeFieldVar};
 use ark_r1cs_std::alloc::AllocVar;
 use ark_r1cs_std::bits::boolean::Boolean;
 use ark_r1cs_std::bits::uint8::UInt8;
+use ark_r1cs_std::eq::EqGadget;
 use ark_r1cs_std::fields::fp::{AllocatedFp, FpVar};
-use ark_r1cs_std::R1CSVar;
+use ark_r1cs_std::{R1CSVar, ToBitsGadget};
 use ark_relations::lc;
 use ark_relations::r1cs::{ConstraintSystemRef, LinearCombination, SynthesisError};
+use ark_std::convert::TryInto;
 use ark_std::vec;
 use ark_std::vec::Vec;
 
 mod absorb;
 pub use absorb::*;
 
+/// Derives `AbsorbGadget` for structs and enums.
+#[cfg(feature = "derive")]
+pub use ark_sponge_derive::AbsorbGadget;
+
+// Items referred to by the code generated by `ark-sponge-derive`.
+#[doc(hidden)]
+pub mod __derive {
+    pub use super::AbsorbGadget;
+    pub use ark_ff::PrimeField;
+    pub use ark_r1cs_std::bits::uint8::UInt8;
+    pub use ark_r1cs_std::fields::fp::FpVar;
+    pub use ark_relations::r1cs::SynthesisError;
+    pub use ark_std::vec::Vec;
+}
+
 /// Converts little-endian bits to a list of nonnative elements.
 pub fn bits_le_to_nonnative<'a, F: PrimeField, CF: PrimeField>(
     cs: ConstraintSystemRef<CF>,
@@ -88,6 +105,77 @@ pub fn bits_le_to_nonnative<'a, F: PrimeField, CF: PrimeField>(
     Ok(output)
 }
 
+/// Reduces the integer with little-endian `bits` modulo `modulus`, given the quotient and the
+/// remainder of the division as a hint.
+///
+/// The quotient and the remainder are allocated as bits, with as many bits as they can have,
+/// and constrained by `bits = quotient * modulus + remainder`. The remainder is kept below
+/// `modulus` by allocating the bits of `modulus - 1 - remainder` too. `bits` must have fewer
+/// bits than the capacity of `CF`, so that the equation does not wrap around.
+fn bits_le_mod<CF: PrimeField>(
+    bits: &[Boolean<CF>],
+    modulus: usize,
+    hint: Option<(Vec<bool>, u64)>,
+) -> Result<FpVar<CF>, SynthesisError> {
+    let cs = bits.cs();
+    let modulus_bits = crate::num_bits(modulus);
+    // The quotient is less than `2^bits.len() / 2^(modulus_bits - 1)`.
+    let quotient_bits = bits.len() + 1 - modulus_bits;
+    let new_bits = |value: Option<Vec<bool>>, len: usize| {
+        (0..len)
+            .map(|i| {
+                let bit = value.as_ref().map(|value| value[i]);
+                if cs.is_none() {
+                    Ok(Boolean::constant(bit.unwrap_or_default()))
+                } else {
+                    Boolean::new_witness(cs.clone(), || {
+                        bit.ok_or(SynthesisError::AssignmentMissing)
+                    })
+                }
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()
+    };
+    let u64_bits = |value: u64| (0..64).map(|i| (value >> i) & 1 == 1).collect::<Vec<_>>();
+
+    let quotient = new_bits(hint.as_ref().map(|(q, _)| q.clone()), quotient_bits)?;
+    let remainder = new_bits(hint.as_ref().map(|(_, r)| u64_bits(*r)), modulus_bits)?;
+    let slack = new_bits(
+        hint.as_ref()
+            .map(|(_, r)| u64_bits((modulus as u64 - 1).wrapping_sub(*r))),
+        modulus_bits,
+    )?;
+
+    let quotient = Boolean::le_bits_to_fp_var(&quotient)?;
+    let remainder = Boolean::le_bits_to_fp_var(&remainder)?;
+    let slack = Boolean::le_bits_to_fp_var(&slack)?;
+
+    let modulus = CF::from(modulus as u64);
+    (quotient * modulus + &remainder).enforce_equal(&Boolean::le_bits_to_fp_var(bits)?)?;
+    (&remainder + slack).enforce_equal(&FpVar::Constant(modulus - CF::one()))?;
+    Ok(remainder)
+}
+
+/// Whether an absorbed value is known to the verifier, see
+/// [`CryptographicSpongeVar::absorb_public`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    /// The value is part of the public input or of the messages sent to the verifier.
+    Public,
+    /// The value is only known to the prover.
+    Private,
+}
+
+/// An entry of the audit log of a sponge: the field elements of one input passed to
+/// `absorb_public` or `absorb_private`, and its visibility.
+#[cfg(feature = "audit")]
+#[derive(Clone, Debug)]
+pub struct AbsorbRecord<CF: PrimeField> {
+    /// The visibility the input was absorbed with
+    pub visibility: Visibility,
+    /// The field elements the input was absorbed as
+    pub elements: Vec<FpVar<CF>>,
+}
+
 /// Enables simple access to the "gadget" version of the sponge.
 /// Simplifies trait bounds in downstream generic code.
 pub trait SpongeWithGadget<CF: PrimeField>: CryptographicSponge {
@@ -111,14 +199,271 @@ pub trait CryptographicSpongeVar<CF: PrimeField, S: CryptographicSponge>: Clone
     /// Absorb an input into the sponge.
     fn absorb(&mut self, input: &impl AbsorbGadget<CF>) -> Result<(), SynthesisError>;
 
+    /// Absorb the UTF-8 bytes of `label`, a string known at circuit-generation time such as a
+    /// domain label.
+    ///
+    /// The bytes are constants, absorbed like a `Vec<UInt8<CF>>` and so prefixed with their
+    /// number. This matches absorbing `label.as_bytes()` with the native sponge.
+    fn absorb_str(&mut self, label: &str) -> Result<(), SynthesisError> {
+        self.absorb(&UInt8::constant_vec(label.as_bytes()))
+    }
+
+    /// Absorb a byte message packed in blocks of `floor(CF::Params::CAPACITY / 8)` bytes, as a
+    /// [`Message`]. The length is a constant. This matches `CryptographicSponge::absorb_message`
+    /// on the same bytes.
+    fn absorb_message(&mut self, message: &[UInt8<CF>]) -> Result<(), SynthesisError> {
+        self.absorb(&Message(message))
+    }
+
+    /// Absorb `label` followed by `input`, so that a message absorbed under the wrong label, or
+    /// in the wrong place, changes the transcript.
+    ///
+    /// The label bytes are constants, absorbed like a `Vec<UInt8<CF>>` and so prefixed with their
+    /// number. This matches absorbing `label` and then the native `input` with the native
+    /// sponge.
+    fn absorb_labeled(
+        &mut self,
+        label: &'static [u8],
+        input: &impl AbsorbGadget<CF>,
+    ) -> Result<(), SynthesisError> {
+        self.absorb(&UInt8::constant_vec(label))?;
+        self.absorb(input)
+    }
+
+    /// Absorb `bits` packed into as few field elements as possible.
+    ///
+    /// The number of bits is absorbed first, as a constant. The bits follow in chunks of
+    /// `CF::Params::CAPACITY` bits, each read as a little-endian integer. Absorbing a `Boolean`
+    /// slice instead takes a full field element per bit.
+    fn absorb_bits_packed(&mut self, bits: &[Boolean<CF>]) -> Result<(), SynthesisError> {
+        let mut elements = vec![FpVar::Constant(CF::from(bits.len() as u64))];
+        for chunk in bits.chunks(CF::Params::CAPACITY as usize) {
+            elements.push(Boolean::le_bits_to_fp_var(chunk)?);
+        }
+        self.absorb(&elements)
+    }
+
+    /// Absorb the opening of a commitment: `value` followed by `randomness`, its blinding factor.
+    ///
+    /// This is the same as absorbing `[value, randomness]`, and matches absorbing the native
+    /// elements in that order. Swapping them changes the transcript.
+    fn absorb_opening(
+        &mut self,
+        value: &FpVar<CF>,
+        randomness: &FpVar<CF>,
+    ) -> Result<(), SynthesisError> {
+        self.absorb(&vec![value.clone(), randomness.clone()])
+    }
+
+    /// Absorb each of `items` in order, as `absorb_gadget!` does, for lists of different types
+    /// that are only known at runtime.
+    ///
+    /// Each item is absorbed as its sponge field elements, which is how `absorb` treats every
+    /// input.
+    fn absorb_many(&mut self, items: &[&dyn AbsorbGadget<CF>]) -> Result<(), SynthesisError> {
+        for item in items {
+            self.absorb(&item.to_sponge_field_elements()?)?;
+        }
+        Ok(())
+    }
+
+    /// Absorb an input that is known to the verifier.
+    ///
+    /// This hashes exactly like `absorb(input)`. With the `audit` feature, the input is also
+    /// recorded with `Visibility::Public` by `record_absorb`, so that a transcript can be audited
+    /// for private values that end up seeding challenges.
+    fn absorb_public(&mut self, input: &impl AbsorbGadget<CF>) -> Result<(), SynthesisError> {
+        let elements = input.to_sponge_field_elements()?;
+        #[cfg(feature = "audit")]
+        self.record_absorb(Visibility::Public, &elements);
+        self.absorb(&elements)
+    }
+
+    /// Absorb an input that is only known to the prover.
+    ///
+    /// This hashes exactly like `absorb(input)`, and records the input with
+    /// `Visibility::Private` like `absorb_public` does.
+    fn absorb_private(&mut self, input: &impl AbsorbGadget<CF>) -> Result<(), SynthesisError> {
+        let elements = input.to_sponge_field_elements()?;
+        #[cfg(feature = "audit")]
+        self.record_absorb(Visibility::Private, &elements);
+        self.absorb(&elements)
+    }
+
+    /// Records an input passed to `absorb_public` or `absorb_private` in the audit log of the
+    /// sponge. Sponges without an audit log ignore it.
+    #[cfg(feature = "audit")]
+    fn record_absorb(&mut self, _visibility: Visibility, _elements: &[FpVar<CF>]) {}
+
+    /// Absorb an input like `absorb`, using `scratch` as the buffer for its field elements.
+    ///
+    /// `scratch` is cleared and refilled with `to_sponge_field_elements_in`, and its elements
+    /// are then absorbed. By default they go through `absorb`, which converts them once more.
+    /// The sponges of this crate override it to absorb `scratch` directly, so that reusing it
+    /// across calls, e.g. in a loop of small absorbs, saves the allocation of a new buffer for
+    /// every input.
+    fn absorb_into(
+        &mut self,
+        input: &impl AbsorbGadget<CF>,
+        scratch: &mut Vec<FpVar<CF>>,
+    ) -> Result<(), SynthesisError> {
+        scratch.clear();
+        input.to_sponge_field_elements_in(scratch)?;
+        self.absorb(&*scratch)
+    }
+
+    /// Absorb `digest`, a field element that commits to data hashed elsewhere.
+    ///
+    /// This is exactly `absorb(digest)`: the sponge neither knows nor checks how the digest was
+    /// computed, and the preimage is not absorbed. The digest binds the transcript to the
+    /// preimage only as far as the hash that produced it is collision resistant, and the
+    /// circuit must constrain that hash separately if the preimage matters.
+    fn absorb_digest(&mut self, digest: &FpVar<CF>) -> Result<(), SynthesisError> {
+        self.absorb(digest)
+    }
+
+    /// Absorb a single field element that is known to have size `size`.
+    ///
+    /// For `Truncated(n)`, this first constrains `elem` to be less than `2^n`, which costs
+    /// `n + 1` constraints, so the absorbed value is guaranteed to be bounded.
+    fn absorb_field_element_with_size(
+        &mut self,
+        elem: &FpVar<CF>,
+        size: FieldElementSize,
+    ) -> Result<(), SynthesisError> {
+        if let FieldElementSize::Truncated(_) = size {
+            let num_bits = size.num_bits::<CF>();
+            let value_bits = elem.value().ok().map(|v| v.into_repr().to_bits_le());
+
+            let mut bits = Vec::with_capacity(num_bits);
+            for i in 0..num_bits {
+                let bit = value_bits.as_ref().map(|value_bits| value_bits[i]);
+                bits.push(if elem.is_constant() {
+                    Boolean::constant(bit.unwrap_or_default())
+                } else {
+                    Boolean::new_witness(elem.cs(), || {
+                        bit.ok_or(SynthesisError::AssignmentMissing)
+                    })?
+                });
+            }
+
+            Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(elem)?;
+        }
+
+        self.absorb(elem)
+    }
+
+    /// Absorb `input` padded to `max_len` field elements.
+    ///
+    /// The sponge absorbs the number of field elements of `input`, those elements, and then
+    /// zeros up to a total of `max_len + 1` elements. The number of permutations, and hence of
+    /// constraints, depends only on `max_len`, so inputs of different lengths yield circuits of
+    /// the same size. The length prefix is a constant, like the length that `UInt8` prefixes to
+    /// a batch, and keeps an input apart from the same input followed by zeros.
+    ///
+    /// Panics if `input` has more than `max_len` field elements.
+    fn absorb_padded(
+        &mut self,
+        input: &impl AbsorbGadget<CF>,
+        max_len: usize,
+    ) -> Result<(), SynthesisError> {
+        let mut elems = input.to_sponge_field_elements()?;
+        assert!(
+            elems.len() <= max_len,
+            "the input has {} field elements, more than the maximum of {}",
+            elems.len(),
+            max_len
+        );
+
+        let mut padded = Vec::with_capacity(max_len + 1);
+        padded.push(FpVar::Constant(CF::from(elems.len() as u64)));
+        padded.append(&mut elems);
+        padded.resize(max_len + 1, FpVar::Constant(CF::zero()));
+        self.absorb(&padded)
+    }
+
+    /// Absorb `bytes` packed into field elements of `bytes_per_element` bytes each.
+    ///
+    /// Each chunk of `bytes_per_element` bytes, the last one possibly shorter, is read as a
+    /// little-endian integer, just like `CF::from_le_bytes_mod_order` does off-circuit. The
+    /// packed elements are linear combinations of the bits of the bytes, so no extra constraints
+    /// are needed. No length is absorbed.
+    ///
+    /// Panics if `bytes_per_element` is zero or if a chunk does not fit in the capacity of `CF`.
+    fn absorb_bytes_packed(
+        &mut self,
+        bytes: &[UInt8<CF>],
+        bytes_per_element: usize,
+    ) -> Result<(), SynthesisError> {
+        assert!(
+            bytes_per_element > 0 && bytes_per_element * 8 <= CF::Params::CAPACITY as usize,
+            "{} bytes do not fit in a single field element",
+            bytes_per_element
+        );
+
+        let mut elems = Vec::new();
+        for chunk in bytes.chunks(bytes_per_element) {
+            elems.push(Boolean::le_bits_to_fp_var(&chunk.to_bits_le()?)?);
+        }
+
+        self.absorb(&elems)
+    }
+
+    /// Absorb the entries of a map, such as the `(key, value)` pairs of a `HashMap`, sorted by
+    /// `key_fn`.
+    ///
+    /// The entries are absorbed in the same order whatever order they are iterated in, as
+    /// long as `key_fn` returns distinct keys; entries with equal keys keep their iteration
+    /// order. The order is fixed when the circuit is built, so `key_fn` should read data that is
+    /// known at that time, such as constants or native labels.
+    fn absorb_map_sorted_by<A: AbsorbGadget<CF>, K: Ord>(
+        &mut self,
+        entries: impl IntoIterator<Item = A>,
+        key_fn: impl Fn(&A) -> K,
+    ) -> Result<(), SynthesisError> {
+        let mut entries: Vec<A> = entries.into_iter().collect();
+        entries.sort_by_key(|entry| key_fn(entry));
+        self.absorb(&entries)
+    }
+
+    /// Absorb `bytes` one byte per field element, as a byte-oriented sponge does.
+    ///
+    /// This is `absorb_bytes_packed(bytes, 1)`, and matches absorbing each byte as a separate
+    /// `u8` with the native sponge. It absorbs many more elements than `absorb(bytes)`, which
+    /// packs the bytes densely and prefixes their number.
+    fn absorb_bytes_unpacked(&mut self, bytes: &[UInt8<CF>]) -> Result<(), SynthesisError> {
+        self.absorb_bytes_packed(bytes, 1)
+    }
+
     /// Squeeze `num_bytes` bytes from the sponge.
     fn squeeze_bytes(&mut self, num_bytes: usize) -> Result<Vec<UInt8<CF>>, SynthesisError>;
 
+    /// Squeeze `num_bytes` bytes from the sponge, in the given byte order.
+    ///
+    /// Little-endian bytes are those of `squeeze_bytes`. Big-endian bytes are the same bytes
+    /// with the order of the whole output reversed, which costs no constraints.
+    fn squeeze_bytes_with_endianness(
+        &mut self,
+        num_bytes: usize,
+        endianness: Endianness,
+    ) -> Result<Vec<UInt8<CF>>, SynthesisError> {
+        let mut bytes = self.squeeze_bytes(num_bytes)?;
+        if endianness == Endianness::Big {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
     /// Squeeze `num_bit` bits from the sponge.
     fn squeeze_bits(&mut self, num_bits: usize) -> Result<Vec<Boolean<CF>>, SynthesisError>;
 
     /// Squeeze `sizes.len()` nonnative field elements from the sponge, where the `i`-th element of
     /// the output has size `sizes[i]`.
+    ///
+    /// Each element is packed from `sizes[i].num_bits::<F>()` squeezed bits, which are returned
+    /// alongside: `F::Params::CAPACITY` bits for `Full`, and `n` bits for `Truncated(n)`. The bits
+    /// are booleans and the limbs are constrained to their linear combinations, so an element
+    /// is provably less than `2^CAPACITY`, and hence than the modulus of `F` for `Full` sizes.
     fn squeeze_nonnative_field_elements_with_sizes<F: PrimeField>(
         &mut self,
         sizes: &[FieldElementSize],
@@ -153,6 +498,9 @@ pub trait CryptographicSpongeVar<CF: PrimeField, S: CryptographicSponge>: Clone
     }
 
     /// Squeeze `num_elements` nonnative field elements from the sponge.
+    ///
+    /// Each element is packed from `F::Params::CAPACITY` squeezed bits, as described in
+    /// [`Self::squeeze_nonnative_field_elements_with_sizes`].
     fn squeeze_nonnative_field_elements<F: PrimeField>(
         &mut self,
         num_elements: usize,
@@ -162,6 +510,66 @@ pub trait CryptographicSpongeVar<CF: PrimeField, S: CryptographicSponge>: Clone
         )
     }
 
+    /// Squeeze `count` indices in `[0, modulus)`, as field elements.
+    ///
+    /// Each index is a squeezed integer of `bits(modulus) + 128` bits reduced modulo `modulus`,
+    /// exactly like `CryptographicSponge::squeeze_indices`, whose documentation discusses the
+    /// bias. The reduction is constrained with the quotient and the remainder as bit-bounded
+    /// hints, which costs about `bits(modulus) + 128` constraints per index on top of the
+    /// squeeze.
+    ///
+    /// Panics if `modulus` is zero, or if `bits(modulus) + 128` is not below the capacity of
+    /// `CF`, which the reduction needs to not wrap around.
+    fn squeeze_indices(
+        &mut self,
+        count: usize,
+        modulus: usize,
+    ) -> Result<Vec<FpVar<CF>>, SynthesisError> {
+        assert!(modulus > 0, "indices need a positive modulus");
+        let bits_per_index = crate::num_bits(modulus) + crate::INDEX_EXTRA_BITS;
+        assert!(
+            bits_per_index < CF::Params::CAPACITY as usize,
+            "indices modulo {} do not fit in a single field element",
+            modulus
+        );
+
+        let bits = self.squeeze_bits(count * bits_per_index)?;
+        let mut indices = Vec::with_capacity(count);
+        for chunk in bits.chunks(bits_per_index) {
+            let hint = chunk
+                .value()
+                .ok()
+                .map(|value| crate::divide_bits_le(&value, modulus as u64));
+            indices.push(bits_le_mod(chunk, modulus, hint)?);
+        }
+        Ok(indices)
+    }
+
+    /// Squeeze a uniformly random permutation of `0..n`, as field elements, by a Fisher-Yates
+    /// shuffle that matches `CryptographicSponge::squeeze_permutation`.
+    ///
+    /// The shuffle starts from the constants `0..n` and swaps the `i`-th position with an index
+    /// squeezed in `[0, i]` by `squeeze_indices`, for `i` from `n - 1` down to 1. Each swap
+    /// compares the index with every position up to `i`, so the output is a permutation by
+    /// construction, at the cost of `O(n^2)` constraints on top of the squeezed indices.
+    fn squeeze_permutation(&mut self, n: usize) -> Result<Vec<FpVar<CF>>, SynthesisError> {
+        let mut permutation: Vec<_> = (0..n)
+            .map(|i| FpVar::Constant(CF::from(i as u64)))
+            .collect();
+        for i in (1..n).rev() {
+            let j = self.squeeze_indices(1, i + 1)?.remove(0);
+            let at_i = permutation[i].clone();
+            let mut at_j = FpVar::Constant(CF::zero());
+            for (k, elem) in permutation.iter_mut().enumerate().take(i + 1) {
+                let is_j = j.is_eq(&FpVar::Constant(CF::from(k as u64)))?;
+                at_j = is_j.select(elem, &at_j)?;
+                *elem = is_j.select(&at_i, elem)?;
+            }
+            permutation[i] = at_j;
+        }
+        Ok(permutation)
+    }
+
     /// Creates a new sponge with applied domain separation.
     fn fork(&self, domain: &[u8]) -> Result<Self, SynthesisError> {
         let mut new_sponge = self.clone();
@@ -185,4 +593,193 @@ pub trait CryptographicSpongeVar<CF: PrimeField, S: CryptographicSponge>: Clone
         &mut self,
         num_elements: usize,
     ) -> Result<Vec<FpVar<CF>>, SynthesisError>;
+
+    /// Squeeze `N` field elements from the sponge, as a fixed-size array.
+    ///
+    /// This is `squeeze_field_elements(N)` for challenges whose number is known at compile time.
+    fn squeeze_array<const N: usize>(&mut self) -> Result<[FpVar<CF>; N], SynthesisError> {
+        let squeezed = self.squeeze_field_elements(N)?;
+        let len = squeezed.len();
+        Ok(squeezed
+            .try_into()
+            .unwrap_or_else(|_| panic!("squeezed {} field elements instead of {}", len, N)))
+    }
+
+    /// Squeeze `sizes.len()` field elements from the sponge, where the `i`-th element of the
+    /// output has size `sizes[i]`.
+    ///
+    /// This matches the native `squeeze_native_field_elements_with_sizes`: if every size is
+    /// `Full`, the elements are squeezed directly. Otherwise, the total number of bits is
+    /// squeezed in one call and split into elements of the requested widths, so every
+    /// `Truncated(n)` element is a combination of `n` bits and therefore less than `2^n`.
+    fn squeeze_field_elements_with_sizes(
+        &mut self,
+        sizes: &[FieldElementSize],
+    ) -> Result<Vec<FpVar<CF>>, SynthesisError> {
+        if sizes.iter().all(|size| *size == FieldElementSize::Full) {
+            return self.squeeze_field_elements(sizes.len());
+        }
+
+        let total_bits = FieldElementSize::sum::<CF>(sizes);
+        let bits = self.squeeze_bits(total_bits)?;
+
+        let mut output = Vec::with_capacity(sizes.len());
+        let mut bits_window = bits.as_slice();
+        for size in sizes {
+            let num_bits = size.num_bits::<CF>();
+            output.push(Boolean::le_bits_to_fp_var(&bits_window[..num_bits])?);
+            bits_window = &bits_window[num_bits..];
+        }
+
+        Ok(output)
+    }
+
+    /// Squeeze `out.len()` field elements from the sponge into `out`.
+    ///
+    /// This produces the same elements as `squeeze_field_elements`, but lets the caller reuse
+    /// the output buffer across calls.
+    fn squeeze_field_elements_into(&mut self, out: &mut [FpVar<CF>]) -> Result<(), SynthesisError> {
+        let elems = self.squeeze_field_elements(out.len())?;
+        out.clone_from_slice(&elems);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraints::{bits_le_mod, bits_le_to_nonnative, CryptographicSpongeVar};
+    use crate::poseidon::constraints::PoseidonSpongeVar;
+    use crate::poseidon::tests::poseidon_parameters_for_test;
+    use crate::poseidon::{find_poseidon_ark_and_mds, PoseidonParameters};
+    use ark_ff::{
+        BigInteger, BigInteger256, FftParameters, Fp256, Fp256Parameters, FpParameters, PrimeField,
+    };
+    use ark_r1cs_std::alloc::AllocVar;
+    use ark_r1cs_std::bits::boolean::Boolean;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_relations::*;
+    use ark_test_curves::bls12_381::{Fq, Fr};
+
+    #[test]
+    fn bits_le_to_nonnative_is_constrained() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value = Fq::from(0x1234_5678_9abc_def0u64);
+        let bits: Vec<_> = value.into_repr().to_bits_le()[..64]
+            .iter()
+            .map(|bit| Boolean::new_witness(ns!(cs, "bit"), || Ok(*bit)).unwrap())
+            .collect();
+
+        let elems = bits_le_to_nonnative::<Fq, Fr>(cs.clone(), [&bits]).unwrap();
+        assert_eq!(elems[0].value().unwrap(), value);
+        assert!(cs.is_satisfied().unwrap());
+
+        // The limbs are witnesses allocated last; forging one must break the constraints.
+        let mut cs_mut = cs.borrow_mut().unwrap();
+        *cs_mut.witness_assignment.last_mut().unwrap() += Fr::from(1u64);
+        drop(cs_mut);
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn squeezed_nonnative_elements_are_in_range() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        sponge
+            .absorb(&FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::from(7u64))).unwrap())
+            .unwrap();
+
+        let (elems, bits) = sponge.squeeze_nonnative_field_elements::<Fq>(3).unwrap();
+        let capacity = <Fq as PrimeField>::Params::CAPACITY as usize;
+        for (elem, elem_bits) in elems.iter().zip(&bits) {
+            assert_eq!(elem_bits.len(), capacity);
+            let value_bits = elem.value().unwrap().into_repr().to_bits_le();
+            assert!(value_bits[capacity..].iter().all(|bit| !bit));
+            assert_eq!(value_bits[..capacity].to_vec(), elem_bits.value().unwrap());
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn bits_le_mod_rejects_forged_remainders() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let modulus = 5;
+        let value = 1232u64;
+        let bits: Vec<_> = (0..16)
+            .map(|i| Boolean::new_witness(ns!(cs, "bit"), || Ok((value >> i) & 1 == 1)).unwrap())
+            .collect();
+        let to_bits = |value: u64| (0..16).map(|i| (value >> i) & 1 == 1).collect::<Vec<_>>();
+
+        let index = bits_le_mod(&bits, modulus, Some((to_bits(246), 2))).unwrap();
+        assert_eq!(index.value().unwrap(), Fr::from(2u64));
+        assert!(cs.is_satisfied().unwrap());
+
+        // `1232 = 245 * 5 + 7` also holds, but 7 is not a valid index.
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let bits: Vec<_> = (0..16)
+            .map(|i| Boolean::new_witness(ns!(cs, "bit"), || Ok((value >> i) & 1 == 1)).unwrap())
+            .collect();
+        let _ = bits_le_mod(&bits, modulus, Some((to_bits(245), 7))).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    /// The prime field of order `p = 2^192 + 15943`, a safe prime, whose capacity is 192 bits:
+    /// as many as an index modulo `2^63` takes.
+    struct F193Parameters;
+
+    impl Fp256Parameters for F193Parameters {}
+    impl FftParameters for F193Parameters {
+        type BigInt = BigInteger256;
+        const TWO_ADICITY: u32 = 1;
+        const TWO_ADIC_ROOT_OF_UNITY: BigInteger256 = BigInteger256([0x0, 0x3e47, 0x0, 0x0]);
+    }
+    impl FpParameters for F193Parameters {
+        const MODULUS: BigInteger256 = BigInteger256([0x3e47, 0x0, 0x0, 0x1]);
+        const MODULUS_BITS: u32 = 193;
+        const CAPACITY: u32 = 192;
+        const REPR_SHAVE_BITS: u32 = 63;
+        const R: BigInteger256 =
+            BigInteger256([0x3e47, 0xffffffffffffc1b9, 0xffffffffffffffff, 0x0]);
+        const R2: BigInteger256 = BigInteger256([0x0, 0x0, 0xf2677b1, 0x0]);
+        const INV: u64 = 0x0ea904a3f9e5f489;
+        // 13, in Montgomery form.
+        const GENERATOR: BigInteger256 =
+            BigInteger256([0x3e47, 0xfffffffffffcd665, 0xffffffffffffffff, 0x0]);
+        const MODULUS_MINUS_ONE_DIV_TWO: BigInteger256 =
+            BigInteger256([0x1f23, 0x0, 0x8000000000000000, 0x0]);
+        const T: BigInteger256 = BigInteger256([0x1f23, 0x0, 0x8000000000000000, 0x0]);
+        const T_MINUS_ONE_DIV_TWO: BigInteger256 =
+            BigInteger256([0xf91, 0x0, 0x4000000000000000, 0x0]);
+    }
+
+    type F193 = Fp256<F193Parameters>;
+
+    fn f193_sponge() -> PoseidonSpongeVar<F193> {
+        let (ark, mds) = find_poseidon_ark_and_mds::<F193>(193, 2, 8, 31, 0);
+        let parameters = PoseidonParameters::new(8, 31, 5, mds, ark, 2, 1);
+        PoseidonSpongeVar::new(ConstraintSystem::new_ref(), &parameters)
+    }
+
+    #[test]
+    fn squeeze_indices_below_capacity() {
+        // An index modulo `2^62` takes `63 + 128 = 191` bits, one fewer than the capacity.
+        let mut sponge = f193_sponge();
+        let modulus = 1 << 62;
+        let indices = sponge.squeeze_indices(2, modulus).unwrap();
+        for index in indices.value().unwrap() {
+            assert!(index < F193::from(modulus as u64));
+        }
+        assert!(sponge.cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "indices modulo 9223372036854775808 do not fit")]
+    fn squeeze_indices_rejects_capacity_width() {
+        // An index modulo `2^63` takes `64 + 128 = 192` bits, the whole capacity, so the
+        // reduction could wrap around the modulus of the field.
+        let mut sponge = f193_sponge();
+        let _ = sponge.squeeze_indices(1, 1 << 63);
+    }
 }
@@ -34,6 +34,14 @@ pub use absorb::*;
 /// [cos]: https://eprint.iacr.org/2019/1076
 pub mod poseidon;
 
+/// The sponge for MiMC
+///
+/// This implementation follows the Feistel-MiMC sponge of circomlib's `MiMCSponge`.
+pub mod mimc;
+
+/// A Fiat-Shamir transcript interface on top of the sponges
+pub mod transcript;
+
 /// An enum for specifying the output field element size.
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum FieldElementSize {
@@ -41,17 +49,21 @@ pub enum FieldElementSize {
     Full,
 
     /// Sample field elements from a subset of the field, specified by the maximum number of bits.
+    /// The number of bits must not exceed the capacity of the field.
     Truncated(usize),
 }
 
 impl FieldElementSize {
     pub(crate) fn num_bits<F: PrimeField>(&self) -> usize {
-        if let FieldElementSize::Truncated(num_bits) = self {
-            if *num_bits > (F::Params::MODULUS_BITS as usize) {
-                panic!("num_bits is greater than the capacity of the field.")
+        match self {
+            FieldElementSize::Full => F::Params::CAPACITY as usize,
+            FieldElementSize::Truncated(num_bits) => {
+                if *num_bits > (F::Params::CAPACITY as usize) {
+                    panic!("num_bits is greater than the capacity of the field.")
+                }
+                *num_bits
             }
-        };
-        F::Params::CAPACITY as usize
+        }
     }
 
     /// Calculate the sum of field element sizes in `elements`.
@@ -83,18 +95,7 @@ pub(crate) fn squeeze_field_elements_with_sizes_default_impl<F: PrimeField>(
         let nonnative_bits_le: Vec<bool> = bits_window[..num_bits].to_vec();
         bits_window = &bits_window[num_bits..];
 
-        let nonnative_bytes = nonnative_bits_le
-            .chunks(8)
-            .map(|bits| {
-                let mut byte = 0u8;
-                for (i, &bit) in bits.into_iter().enumerate() {
-                    if bit {
-                        byte += 1 << i;
-                    }
-                }
-                byte
-            })
-            .collect::<Vec<_>>();
+        let nonnative_bytes = bits_le_to_bytes_le(&nonnative_bits_le);
 
         output.push(F::from_le_bytes_mod_order(nonnative_bytes.as_slice()));
     }
@@ -102,6 +103,45 @@ pub(crate) fn squeeze_field_elements_with_sizes_default_impl<F: PrimeField>(
     output
 }
 
+/// Packs little-endian bits into little-endian bytes, padding the last byte with zeros.
+fn bits_le_to_bytes_le(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|bits| {
+            let mut byte = 0u8;
+            for (i, &bit) in bits.iter().enumerate() {
+                if bit {
+                    byte += 1 << i;
+                }
+            }
+            byte
+        })
+        .collect()
+}
+
+/// The number of bits squeezed for an index or a uniform field element on top of the bit length
+/// of its modulus, which keeps the bias of the modular reduction below `2^-128`.
+pub(crate) const INDEX_EXTRA_BITS: usize = 128;
+
+/// The bit length of `modulus`.
+pub(crate) fn num_bits(modulus: usize) -> usize {
+    (usize::BITS - modulus.leading_zeros()) as usize
+}
+
+/// Divides the integer with little-endian `bits` by `modulus` with schoolbook long division.
+/// Returns the little-endian bits of the quotient, as many as `bits`, and the remainder.
+pub(crate) fn divide_bits_le(bits: &[bool], modulus: u64) -> (Vec<bool>, u64) {
+    let mut quotient = vec![false; bits.len()];
+    let mut remainder = 0u128;
+    for (i, bit) in bits.iter().enumerate().rev() {
+        remainder = (remainder << 1) | (*bit as u128);
+        if remainder >= modulus as u128 {
+            remainder -= modulus as u128;
+            quotient[i] = true;
+        }
+    }
+    (quotient, remainder as u64)
+}
+
 /// The interface for a cryptographic sponge.
 /// A sponge can `absorb` or take in inputs and later `squeeze` or output bytes or field elements.
 /// The outputs are dependent on previous `absorb` and `squeeze` calls.
@@ -148,6 +188,64 @@ pub trait CryptographicSponge: Clone {
         )
     }
 
+    /// Squeeze `num_elements` field elements, each uniformly distributed over all of `F` up to
+    /// a statistical distance of `2^-128`.
+    ///
+    /// `squeeze_field_elements` only outputs elements below `2^{F::Params::CAPACITY}`, which can
+    /// be much less than the modulus. Each element here is instead an integer of
+    /// `F::Params::MODULUS_BITS + 128` squeezed bits reduced modulo the modulus `p`. As
+    /// `2^{MODULUS_BITS} > p`, the reduction favors small residues by at most `2^-128` in
+    /// statistical distance, at the cost of squeezing `128 + MODULUS_BITS - CAPACITY` more bits
+    /// per element.
+    fn squeeze_field_elements_uniform<F: PrimeField>(&mut self, num_elements: usize) -> Vec<F> {
+        let bits_per_element = F::Params::MODULUS_BITS as usize + INDEX_EXTRA_BITS;
+
+        self.squeeze_bits(num_elements * bits_per_element)
+            .chunks(bits_per_element)
+            .map(|bits| F::from_le_bytes_mod_order(&bits_le_to_bytes_le(bits)))
+            .collect()
+    }
+
+    /// Squeeze `count` indices in `[0, modulus)`.
+    ///
+    /// Each index is a squeezed integer of `bits(modulus) + 128` bits, that is 128 more bits
+    /// than the modulus has, reduced modulo `modulus`. Unless `modulus` is a power
+    /// of two, the reduction favors small indices, but the statistical distance from uniform
+    /// is at most `2^-128`. The indices match the ones of
+    /// `CryptographicSpongeVar::squeeze_indices`.
+    ///
+    /// Panics if `modulus` is zero.
+    fn squeeze_indices(&mut self, count: usize, modulus: usize) -> Vec<usize> {
+        assert!(modulus > 0, "indices need a positive modulus");
+        let bits_per_index = num_bits(modulus) + INDEX_EXTRA_BITS;
+
+        self.squeeze_bits(count * bits_per_index)
+            .chunks(bits_per_index)
+            .map(|bits| divide_bits_le(bits, modulus as u64).1 as usize)
+            .collect()
+    }
+
+    /// Squeeze a uniformly random permutation of `0..n`, by a Fisher-Yates shuffle.
+    ///
+    /// For `i` from `n - 1` down to 1, the `i`-th position is swapped with an index squeezed
+    /// in `[0, i]` by `squeeze_indices(1, i + 1)`. The permutation matches the one of
+    /// `CryptographicSpongeVar::squeeze_permutation`.
+    fn squeeze_permutation(&mut self, n: usize) -> Vec<usize> {
+        let mut permutation: Vec<usize> = (0..n).collect();
+        for i in (1..n).rev() {
+            let j = self.squeeze_indices(1, i + 1)[0];
+            permutation.swap(i, j);
+        }
+        permutation
+    }
+
+    /// Absorb a byte message packed in blocks of `floor(CAPACITY / 8)` bytes, as a
+    /// [`Message`]: its length, then one field element per block, with the final short block
+    /// padded with zeros.
+    fn absorb_message(&mut self, message: &[u8]) {
+        self.absorb(&Message(message))
+    }
+
     /// Creates a new sponge with applied domain separation.
     fn fork(&self, domain: &[u8]) -> Self {
         let mut new_sponge = self.clone();
@@ -197,6 +295,74 @@ pub trait SpongeExt: CryptographicSponge {
     fn into_state(self) -> Self::State;
 }
 
+/// The state of a native sponge, as a vector of field elements that it dereferences to.
+///
+/// With the `zeroize` feature, the elements are overwritten with zeros when the state is
+/// dropped. This only clears the current buffer: copies made by `clone` or by the compiler when
+/// the state is moved, buffers left behind when the vector grows or is replaced through
+/// `DerefMut`, and vectors taken out by `into_inner` are not cleared.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateVec<F: PrimeField>(Vec<F>);
+
+impl<F: PrimeField> StateVec<F> {
+    /// Returns the elements of the state, which are no longer zeroized on drop.
+    pub fn into_inner(mut self) -> Vec<F> {
+        ark_std::mem::take(&mut self.0)
+    }
+}
+
+impl<F: PrimeField> From<Vec<F>> for StateVec<F> {
+    fn from(elements: Vec<F>) -> Self {
+        Self(elements)
+    }
+}
+
+impl<F: PrimeField> ark_std::ops::Deref for StateVec<F> {
+    type Target = Vec<F>;
+
+    fn deref(&self) -> &Vec<F> {
+        &self.0
+    }
+}
+
+impl<F: PrimeField> ark_std::ops::DerefMut for StateVec<F> {
+    fn deref_mut(&mut self) -> &mut Vec<F> {
+        &mut self.0
+    }
+}
+
+impl<F: PrimeField> PartialEq<Vec<F>> for StateVec<F> {
+    fn eq(&self, other: &Vec<F>) -> bool {
+        self.0 == *other
+    }
+}
+
+impl<F: PrimeField> PartialEq<StateVec<F>> for Vec<F> {
+    fn eq(&self, other: &StateVec<F>) -> bool {
+        *self == other.0
+    }
+}
+
+/// Overwrites the elements with zeros, keeping their number.
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> zeroize::Zeroize for StateVec<F> {
+    fn zeroize(&mut self) {
+        for elem in self.0.iter_mut() {
+            elem.zeroize();
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> Drop for StateVec<F> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> zeroize::ZeroizeOnDrop for StateVec<F> {}
+
 /// The mode structure for duplex sponges
 #[derive(Clone, Debug)]
 pub enum DuplexSpongeMode {
@@ -211,3 +377,17 @@ pub enum DuplexSpongeMode {
         next_squeeze_index: usize,
     },
 }
+
+/// Describes the mode and the next rate index, e.g. `absorbing at rate index 1`.
+impl ark_std::fmt::Display for DuplexSpongeMode {
+    fn fmt(&self, f: &mut ark_std::fmt::Formatter<'_>) -> ark_std::fmt::Result {
+        match self {
+            DuplexSpongeMode::Absorbing { next_absorb_index } => {
+                write!(f, "absorbing at rate index {}", next_absorb_index)
+            }
+            DuplexSpongeMode::Squeezing { next_squeeze_index } => {
+                write!(f, "squeezing at rate index {}", next_squeeze_index)
+            }
+        }
+    }
+}
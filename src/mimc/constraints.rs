@@ -0,0 +1,223 @@
+use crate::constraints::AbsorbGadget;
+use crate::constraints::{CryptographicSpongeVar, SpongeWithGadget};
+use crate::mimc::{MimcParameters, MimcSponge};
+use crate::DuplexSpongeMode;
+use ark_ff::{FpParameters, PrimeField};
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::vec;
+use ark_std::vec::Vec;
+
+#[derive(Clone)]
+/// the gadget for MiMC sponge
+///
+/// The state is a pair `(xL, xR)` of field elements: `xL` is the rate and `xR` is the
+/// capacity. Every round costs as many constraints as the `x^alpha` S-box.
+pub struct MimcSpongeVar<F: PrimeField> {
+    /// Constraint system
+    pub cs: ConstraintSystemRef<F>,
+
+    /// Sponge Parameters
+    pub parameters: MimcParameters<F>,
+
+    // Sponge State
+    /// The sponge's state `[xL, xR]`
+    pub state: Vec<FpVar<F>>,
+    /// The mode
+    pub mode: DuplexSpongeMode,
+}
+
+impl<F: PrimeField> SpongeWithGadget<F> for MimcSponge<F> {
+    type Var = MimcSpongeVar<F>;
+}
+
+impl<F: PrimeField> MimcSpongeVar<F> {
+    #[tracing::instrument(target = "r1cs", skip(self))]
+    fn permute(&mut self) -> Result<(), SynthesisError> {
+        let rounds = self.parameters.rounds();
+        let mut x_l = self.state[0].clone();
+        let mut x_r = self.state[1].clone();
+        for (i, c) in self.parameters.round_constants.iter().enumerate() {
+            let t = &x_l + (self.parameters.key + c);
+            let t_alpha = t.pow_by_constant([self.parameters.alpha])?;
+            // The halves are swapped in every round except the last one.
+            if i < rounds - 1 {
+                let new_x_l = x_r + t_alpha;
+                x_r = x_l;
+                x_l = new_x_l;
+            } else {
+                x_r += t_alpha;
+            }
+        }
+        self.state[0] = x_l;
+        self.state[1] = x_r;
+        Ok(())
+    }
+
+    fn absorb_elements(&mut self, elements: &[FpVar<F>]) -> Result<(), SynthesisError> {
+        for elem in elements {
+            // The rate is a single element, so the state is permuted before every absorption
+            // except the first one after the sponge is created.
+            match self.mode {
+                DuplexSpongeMode::Absorbing {
+                    next_absorb_index: 0,
+                } => {}
+                _ => self.permute()?,
+            }
+            self.state[0] += elem;
+            self.mode = DuplexSpongeMode::Absorbing {
+                next_absorb_index: 1,
+            };
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> CryptographicSpongeVar<F, MimcSponge<F>> for MimcSpongeVar<F> {
+    type Parameters = MimcParameters<F>;
+
+    #[tracing::instrument(target = "r1cs", skip(cs))]
+    fn new(cs: ConstraintSystemRef<F>, parameters: &MimcParameters<F>) -> Self {
+        Self {
+            cs,
+            parameters: parameters.clone(),
+            state: vec![FpVar::zero(); 2],
+            mode: DuplexSpongeMode::Absorbing {
+                next_absorb_index: 0,
+            },
+        }
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(self))]
+    fn cs(&self) -> ConstraintSystemRef<F> {
+        self.cs.clone()
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(self, input))]
+    fn absorb(&mut self, input: &impl AbsorbGadget<F>) -> Result<(), SynthesisError> {
+        self.absorb_elements(&input.to_sponge_field_elements()?)
+    }
+
+    fn absorb_into(
+        &mut self,
+        input: &impl AbsorbGadget<F>,
+        scratch: &mut Vec<FpVar<F>>,
+    ) -> Result<(), SynthesisError> {
+        scratch.clear();
+        input.to_sponge_field_elements_in(scratch)?;
+        self.absorb_elements(scratch)
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(self))]
+    fn squeeze_bytes(&mut self, num_bytes: usize) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        let usable_bytes = (F::Params::CAPACITY / 8) as usize;
+
+        let num_elements = (num_bytes + usable_bytes - 1) / usable_bytes;
+        let src_elements = self.squeeze_field_elements(num_elements)?;
+
+        let mut bytes: Vec<UInt8<F>> = Vec::with_capacity(usable_bytes * num_elements);
+        for elem in &src_elements {
+            bytes.extend_from_slice(&elem.to_bytes()?[..usable_bytes]);
+        }
+
+        bytes.truncate(num_bytes);
+        Ok(bytes)
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(self))]
+    fn squeeze_bits(&mut self, num_bits: usize) -> Result<Vec<Boolean<F>>, SynthesisError> {
+        let usable_bits = F::Params::CAPACITY as usize;
+
+        let num_elements = (num_bits + usable_bits - 1) / usable_bits;
+        let src_elements = self.squeeze_field_elements(num_elements)?;
+
+        let mut bits: Vec<Boolean<F>> = Vec::with_capacity(usable_bits * num_elements);
+        for elem in &src_elements {
+            bits.extend_from_slice(&elem.to_bits_le()?[..usable_bits]);
+        }
+
+        bits.truncate(num_bits);
+        Ok(bits)
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(self))]
+    fn squeeze_field_elements(
+        &mut self,
+        num_elements: usize,
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut squeezed_elems = vec![FpVar::zero(); num_elements];
+        self.squeeze_field_elements_into(&mut squeezed_elems)?;
+
+        Ok(squeezed_elems)
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(self, out))]
+    fn squeeze_field_elements_into(&mut self, out: &mut [FpVar<F>]) -> Result<(), SynthesisError> {
+        for elem in out.iter_mut() {
+            self.permute()?;
+            *elem = self.state[0].clone();
+            self.mode = DuplexSpongeMode::Squeezing {
+                next_squeeze_index: 1,
+            };
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraints::CryptographicSpongeVar;
+    use crate::mimc::constraints::MimcSpongeVar;
+    use crate::mimc::{MimcParameters, MimcSponge};
+    use crate::{CryptographicSponge, FieldBasedCryptographicSponge};
+    use ark_bn254::Fr;
+    use ark_ff::UniformRand;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_relations::*;
+    use ark_std::test_rng;
+
+    #[test]
+    fn absorb_squeeze_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+
+        let absorb: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let absorb_var: Vec<_> = absorb
+            .iter()
+            .map(|v| FpVar::new_witness(ns!(cs, "absorb"), || Ok(*v)).unwrap())
+            .collect();
+
+        let parameters = MimcParameters::circomlib(Fr::rand(&mut rng));
+        let mut native_sponge = MimcSponge::<Fr>::new(&parameters);
+        let mut constraint_sponge = MimcSpongeVar::<Fr>::new(cs.clone(), &parameters);
+
+        native_sponge.absorb(&absorb);
+        constraint_sponge.absorb(&absorb_var).unwrap();
+
+        let squeeze1 = native_sponge.squeeze_native_field_elements(2);
+        let squeeze2 = constraint_sponge.squeeze_field_elements(2).unwrap();
+
+        assert_eq!(squeeze2.value().unwrap(), squeeze1);
+        assert!(cs.is_satisfied().unwrap());
+
+        // `x^5` costs three multiplications per round, and there are four permutations.
+        assert_eq!(cs.num_constraints(), 4 * 220 * 3);
+
+        let mut scratch = Vec::new();
+        let mut into_sponge = MimcSpongeVar::<Fr>::new(cs.clone(), &parameters);
+        into_sponge.absorb_into(&absorb_var, &mut scratch).unwrap();
+        assert_eq!(
+            into_sponge
+                .squeeze_field_elements(2)
+                .unwrap()
+                .value()
+                .unwrap(),
+            squeeze1
+        );
+    }
+}
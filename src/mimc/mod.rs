@@ -0,0 +1,317 @@
+use crate::{
+    batch_field_cast, squeeze_field_elements_with_sizes_default_impl, Absorb, CryptographicSponge,
+    DuplexSpongeMode, FieldBasedCryptographicSponge, FieldElementSize, SpongeExt, StateVec,
+};
+use ark_ff::{BigInteger, FpParameters, PrimeField};
+use ark_std::any::TypeId;
+use ark_std::vec;
+use ark_std::vec::Vec;
+
+/// constraints for MiMC
+#[cfg(feature = "r1cs")]
+pub mod constraints;
+
+mod circomlib;
+
+/// Parameters of the Feistel-MiMC permutation.
+#[derive(Clone, Debug)]
+pub struct MimcParameters<F: PrimeField> {
+    /// Exponent used in the S-box of every round.
+    pub alpha: u64,
+    /// The key added to the left half of the state in every round.
+    pub key: F,
+    /// Round constants, one per round. They are added to the left half of the state together
+    /// with `key` before the S-box is applied.
+    pub round_constants: Vec<F>,
+}
+
+impl<F: PrimeField> MimcParameters<F> {
+    /// Initialize the parameters for the MiMC sponge.
+    pub fn new(alpha: u64, key: F, round_constants: Vec<F>) -> Self {
+        assert!(!round_constants.is_empty(), "MiMC needs at least one round");
+        Self {
+            alpha,
+            key,
+            round_constants,
+        }
+    }
+
+    /// The parameters of circomlib's `MiMCSponge` (220 rounds, `x^5` S-box) with the given `key`.
+    ///
+    /// Panics if `F` is not the BN254 scalar field.
+    pub fn circomlib(key: F) -> Self {
+        // The BN254 scalar field modulus, in little-endian limbs.
+        const BN254_MODULUS: [u64; 4] = [
+            0x43e1f593f0000001,
+            0x2833e84879b97091,
+            0xb85045b68181585d,
+            0x30644e72e131a029,
+        ];
+        assert_eq!(
+            F::characteristic(),
+            &BN254_MODULUS[..],
+            "the circomlib MiMC constants are defined over the BN254 scalar field"
+        );
+
+        let round_constants = circomlib::ROUND_CONSTANTS
+            .iter()
+            .map(|c| F::from_be_bytes_mod_order(&decode_hex(c)))
+            .collect();
+        Self::new(5, key, round_constants)
+    }
+
+    /// The number of rounds of the permutation.
+    pub fn rounds(&self) -> usize {
+        self.round_constants.len()
+    }
+}
+
+fn decode_hex(s: &str) -> Vec<u8> {
+    let digits = s.trim_start_matches("0x").as_bytes();
+    let nibble = |c: u8| (c as char).to_digit(16).expect("invalid hex digit") as u8;
+    digits
+        .chunks(2)
+        .map(|pair| pair.iter().fold(0u8, |acc, c| (acc << 4) | nibble(*c)))
+        .collect()
+}
+
+#[derive(Clone)]
+/// A duplex sponge using the Feistel-MiMC permutation.
+///
+/// The state is a pair `(xL, xR)` of field elements: `xL` is the rate and `xR` is the
+/// capacity. Inputs are added to `xL` and outputs are read from `xL`, so absorbing inputs and
+/// squeezing outputs matches circomlib's `MiMCSponge` template.
+pub struct MimcSponge<F: PrimeField> {
+    /// Sponge Parameters
+    pub parameters: MimcParameters<F>,
+
+    // Sponge State
+    /// Current sponge's state `[xL, xR]`
+    pub state: StateVec<F>,
+    /// Current mode (whether its absorbing or squeezing)
+    pub mode: DuplexSpongeMode,
+}
+
+impl<F: PrimeField> MimcSponge<F> {
+    fn permute(&mut self) {
+        let rounds = self.parameters.rounds();
+        let (mut x_l, mut x_r) = (self.state[0], self.state[1]);
+        for (i, c) in self.parameters.round_constants.iter().enumerate() {
+            let t = x_l + self.parameters.key + c;
+            let t_alpha = t.pow([self.parameters.alpha]);
+            // The halves are swapped in every round except the last one.
+            if i < rounds - 1 {
+                let new_x_l = x_r + t_alpha;
+                x_r = x_l;
+                x_l = new_x_l;
+            } else {
+                x_r += t_alpha;
+            }
+        }
+        self.state[0] = x_l;
+        self.state[1] = x_r;
+    }
+}
+
+impl<F: PrimeField> CryptographicSponge for MimcSponge<F> {
+    type Parameters = MimcParameters<F>;
+
+    fn new(parameters: &Self::Parameters) -> Self {
+        Self {
+            parameters: parameters.clone(),
+            state: vec![F::zero(); 2].into(),
+            mode: DuplexSpongeMode::Absorbing {
+                next_absorb_index: 0,
+            },
+        }
+    }
+
+    fn absorb(&mut self, input: &impl Absorb) {
+        for elem in input.to_sponge_field_elements_as_vec::<F>() {
+            // The rate is a single element, so the state is permuted before every absorption
+            // except the first one after the sponge is created.
+            match self.mode {
+                DuplexSpongeMode::Absorbing {
+                    next_absorb_index: 0,
+                } => {}
+                _ => self.permute(),
+            }
+            self.state[0] += elem;
+            self.mode = DuplexSpongeMode::Absorbing {
+                next_absorb_index: 1,
+            };
+        }
+    }
+
+    fn squeeze_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
+        let usable_bytes = (F::Params::CAPACITY / 8) as usize;
+
+        let num_elements = (num_bytes + usable_bytes - 1) / usable_bytes;
+        let src_elements = self.squeeze_native_field_elements(num_elements);
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(usable_bytes * num_elements);
+        for elem in &src_elements {
+            let elem_bytes = elem.into_repr().to_bytes_le();
+            bytes.extend_from_slice(&elem_bytes[..usable_bytes]);
+        }
+
+        bytes.truncate(num_bytes);
+        bytes
+    }
+
+    fn squeeze_bits(&mut self, num_bits: usize) -> Vec<bool> {
+        let usable_bits = F::Params::CAPACITY as usize;
+
+        let num_elements = (num_bits + usable_bits - 1) / usable_bits;
+        let src_elements = self.squeeze_native_field_elements(num_elements);
+
+        let mut bits: Vec<bool> = Vec::with_capacity(usable_bits * num_elements);
+        for elem in &src_elements {
+            let elem_bits = elem.into_repr().to_bits_le();
+            bits.extend_from_slice(&elem_bits[..usable_bits]);
+        }
+
+        bits.truncate(num_bits);
+        bits
+    }
+
+    fn squeeze_field_elements_with_sizes<F2: PrimeField>(
+        &mut self,
+        sizes: &[FieldElementSize],
+    ) -> Vec<F2> {
+        if F::characteristic() == F2::characteristic() {
+            // native case
+            let mut buf = Vec::with_capacity(sizes.len());
+            batch_field_cast(
+                &self.squeeze_native_field_elements_with_sizes(sizes),
+                &mut buf,
+            )
+            .unwrap();
+            buf
+        } else {
+            squeeze_field_elements_with_sizes_default_impl(self, sizes)
+        }
+    }
+
+    fn squeeze_field_elements<F2: PrimeField>(&mut self, num_elements: usize) -> Vec<F2> {
+        if TypeId::of::<F>() == TypeId::of::<F2>() {
+            let result = self.squeeze_native_field_elements(num_elements);
+            let mut cast = Vec::with_capacity(result.len());
+            batch_field_cast(&result, &mut cast).unwrap();
+            cast
+        } else {
+            self.squeeze_field_elements_with_sizes::<F2>(
+                vec![FieldElementSize::Full; num_elements].as_slice(),
+            )
+        }
+    }
+}
+
+impl<F: PrimeField> FieldBasedCryptographicSponge<F> for MimcSponge<F> {
+    fn squeeze_native_field_elements(&mut self, num_elements: usize) -> Vec<F> {
+        let mut squeezed_elems = Vec::with_capacity(num_elements);
+        for _ in 0..num_elements {
+            self.permute();
+            squeezed_elems.push(self.state[0]);
+            self.mode = DuplexSpongeMode::Squeezing {
+                next_squeeze_index: 1,
+            };
+        }
+
+        squeezed_elems
+    }
+}
+
+#[derive(Clone)]
+/// Stores the state of a MiMC Sponge. Does not store any parameter.
+pub struct MimcSpongeState<F: PrimeField> {
+    state: StateVec<F>,
+    mode: DuplexSpongeMode,
+}
+
+impl<CF: PrimeField> SpongeExt for MimcSponge<CF> {
+    type State = MimcSpongeState<CF>;
+
+    fn from_state(state: Self::State, params: &Self::Parameters) -> Self {
+        let mut sponge = Self::new(params);
+        sponge.mode = state.mode;
+        sponge.state = state.state;
+        sponge
+    }
+
+    fn into_state(self) -> Self::State {
+        Self::State {
+            state: self.state,
+            mode: self.mode,
+        }
+    }
+}
+
+/// Overwrites the state with zeros, keeping its length. The parameters and the mode are not
+/// secret and are left untouched.
+///
+/// The state is also zeroized when the sponge is dropped, as it is a [`StateVec`].
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> zeroize::Zeroize for MimcSponge<F> {
+    fn zeroize(&mut self) {
+        self.state.zeroize()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> zeroize::ZeroizeOnDrop for MimcSponge<F> {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::mimc::{decode_hex, MimcParameters, MimcSponge};
+    use crate::{CryptographicSponge, FieldBasedCryptographicSponge};
+    use ark_bn254::Fr;
+    use ark_ff::{PrimeField, Zero};
+
+    fn from_hex(s: &str) -> Fr {
+        Fr::from_be_bytes_mod_order(&decode_hex(s))
+    }
+
+    #[test]
+    fn test_circomlib_merkle_zeros() {
+        // Tornado Cash hashes Merkle tree nodes as `MiMCSponge([left, right], 0, 1)` and
+        // hard-codes the empty subtree roots, starting from `keccak256("tornado") mod r`.
+        let zeros = [
+            "0x2fe54c60d3acabf3343a35b6eba15db4821b340f76e741e2249685ed4899af6c",
+            "0x256a6135777eee2fd26f54b8b7037a25439d5235caee224154186d2b8a52e31d",
+            "0x1151949895e82ab19924de92c40a3d6f7bcb60d92b00504b8199613683f0c200",
+        ];
+        let parameters = MimcParameters::circomlib(Fr::zero());
+
+        for pair in zeros.windows(2) {
+            let child = from_hex(pair[0]);
+            let mut sponge = MimcSponge::new(&parameters);
+            sponge.absorb(&vec![child, child]);
+            assert_eq!(
+                sponge.squeeze_native_field_elements(1),
+                vec![from_hex(pair[1])]
+            );
+        }
+    }
+
+    #[test]
+    fn test_absorb_matches_circomlib_sponge() {
+        // circomlib's `MiMCSponge` adds each input to `xL` and applies the Feistel permutation,
+        // then outputs `xL`, applying the permutation once more between consecutive outputs.
+        let parameters = MimcParameters::circomlib(Fr::zero());
+        let inputs = [Fr::from(1u64), Fr::from(2u64), Fr::from(3u64)];
+
+        let mut expected = MimcSponge::new(&parameters);
+        for input in &inputs {
+            expected.state[0] += input;
+            expected.permute();
+        }
+        let first = expected.state[0];
+        expected.permute();
+        let second = expected.state[0];
+
+        let mut sponge = MimcSponge::new(&parameters);
+        sponge.absorb(&inputs.to_vec());
+        assert_eq!(sponge.squeeze_native_field_elements(2), vec![first, second]);
+    }
+}
@@ -1,11 +1,17 @@
 use crate::constraints::AbsorbGadget;
+#[cfg(feature = "audit")]
+use crate::constraints::{AbsorbRecord, Visibility};
 use crate::constraints::{CryptographicSpongeVar, SpongeWithGadget};
-use crate::poseidon::{PoseidonParameters, PoseidonSponge};
+#[cfg(feature = "poseidon-trace")]
+use crate::poseidon::HexState;
+use crate::poseidon::{multirate_padding, AbsorbMode, PoseidonParameters, PoseidonSponge};
 use crate::DuplexSpongeMode;
 use ark_ff::{FpParameters, PrimeField};
 use ark_r1cs_std::fields::fp::FpVar;
 use ark_r1cs_std::prelude::*;
 use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::format;
+use ark_std::string::String;
 use ark_std::vec;
 use ark_std::vec::Vec;
 
@@ -15,6 +21,10 @@ use ark_std::vec::Vec;
 /// This implementation of Poseidon is entirely from Fractal's implementation in [COS20][cos]
 /// with small syntax changes.
 ///
+/// The gadget can be created with `ConstraintSystemRef::None`: as long as only constants are
+/// absorbed, every operation is constant-folded and the outputs are constants, which is handy to
+/// precompute fixed hashes.
+///
 /// [cos]: https://eprint.iacr.org/2019/1076
 pub struct PoseidonSpongeVar<F: PrimeField> {
     /// Constraint system
@@ -25,9 +35,22 @@ pub struct PoseidonSpongeVar<F: PrimeField> {
 
     // Sponge State
     /// The sponge's state
+    ///
+    /// It must always hold `parameters.rate + parameters.capacity` elements. This is checked by
+    /// debug assertions when the state is permuted, absorbed into or squeezed from.
     pub state: Vec<FpVar<F>>,
     /// The mode
     pub mode: DuplexSpongeMode,
+    /// The number of permutations applied to the state so far
+    permutation_count: usize,
+    /// Whether absorbing after a squeeze is rejected, see [`PoseidonSpongeVar::new_strict`]
+    strict: bool,
+    /// The inputs passed to `absorb_public` and `absorb_private`, in order
+    #[cfg(feature = "audit")]
+    audit_log: Vec<AbsorbRecord<F>>,
+    /// A fingerprint of the state after each permutation, see [`PoseidonSpongeVar::fingerprint`]
+    #[cfg(feature = "debug")]
+    fingerprint: Vec<F>,
 }
 
 impl<F: PrimeField> SpongeWithGadget<F> for PoseidonSponge<F> {
@@ -35,42 +58,240 @@ impl<F: PrimeField> SpongeWithGadget<F> for PoseidonSponge<F> {
 }
 
 impl<F: PrimeField> PoseidonSpongeVar<F> {
+    /// Returns the number of permutations applied to the state since the sponge was created.
+    ///
+    /// Two transcripts that followed the same absorb/squeeze schedule report the same count,
+    /// which makes this cheap counter handy when debugging prover/verifier mismatches.
+    pub fn permutation_count(&self) -> usize {
+        self.permutation_count
+    }
+
+    /// Initializes a sponge for one-shot hashing, whose `absorb` fails once the sponge has been
+    /// squeezed instead of permuting and continuing. Sponges created with `new` keep allowing
+    /// absorbs after squeezes.
+    ///
+    /// `SynthesisError` has no variant for misuse of a gadget, so the failure is reported as
+    /// `SynthesisError::UnconstrainedVariable`: the late input could not affect the digest that
+    /// has already been squeezed. The constraint system itself is left untouched.
+    pub fn new_strict(cs: ConstraintSystemRef<F>, parameters: &PoseidonParameters<F>) -> Self {
+        let mut sponge = Self::new(cs, parameters);
+        sponge.strict = true;
+        sponge
+    }
+
+    /// Initializes a sponge that resumes the transcript of `native`, with its parameters, state
+    /// and mode. The state is copied as constants, so it is fixed in the circuit and must not
+    /// depend on secret values.
+    ///
+    /// The permutation count starts over from zero.
+    pub fn from_native(cs: ConstraintSystemRef<F>, native: &PoseidonSponge<F>) -> Self {
+        let mut sponge = Self::new(cs, &native.parameters);
+        sponge.state = native.state.iter().copied().map(FpVar::Constant).collect();
+        sponge.mode = native.mode.clone();
+        sponge
+    }
+
+    /// Adds `tag` to the capacity elements of the state, which absorbs only ever leave alone,
+    /// for IV-style domain separation. Poseidon keeps its capacity in the first
+    /// `parameters.capacity` positions of the state. To fix the IV in the parameters instead,
+    /// for the native sponge too, set `PoseidonParameters::initial_state_iv`.
+    ///
+    /// Call this right after creating the sponge and before absorbing anything. Panics if `tag`
+    /// has more elements than the capacity, or if the sponge has already absorbed into its rate
+    /// or been permuted.
+    pub fn absorb_into_capacity(&mut self, tag: &[FpVar<F>]) {
+        assert!(
+            tag.len() <= self.parameters.capacity,
+            "a tag of {} elements does not fit in a capacity of {}",
+            tag.len(),
+            self.parameters.capacity
+        );
+        assert!(
+            self.permutation_count == 0
+                && matches!(
+                    self.mode,
+                    DuplexSpongeMode::Absorbing {
+                        next_absorb_index: 0
+                    }
+                ),
+            "the capacity can only be tagged before the sponge is used"
+        );
+        for (state_elem, tag_elem) in self.state.iter_mut().zip(tag) {
+            *state_elem += tag_elem;
+        }
+    }
+
+    /// Squeezes `num_bits` bits like `squeeze_bits`, but with the bits of each squeezed element
+    /// in big-endian order, as [`PoseidonSponge::squeeze_bits_be`] does. Reordering the bits
+    /// costs no constraints.
+    pub fn squeeze_bits_be(&mut self, num_bits: usize) -> Result<Vec<Boolean<F>>, SynthesisError> {
+        let usable_bits = F::Params::CAPACITY as usize;
+        Ok(self
+            .squeeze_bits(num_bits)?
+            .chunks(usable_bits)
+            .flat_map(|group| group.iter().rev().cloned())
+            .collect())
+    }
+
+    /// Permutes the state and then overwrites its rate with zeros, leaving the sponge ready to
+    /// absorb into a fresh block.
+    ///
+    /// The permutation is invertible, but not without the rate elements it output, so once they
+    /// are cleared a later state no longer determines the states and outputs that came before
+    /// it. Ratchet after squeezing a secret to keep it from being recovered from the state.
+    #[tracing::instrument(target = "r1cs", skip(self))]
+    pub fn ratchet(&mut self) -> Result<(), SynthesisError> {
+        self.permute()?;
+        for elem in self.state[self.parameters.capacity..].iter_mut() {
+            *elem = FpVar::zero();
+        }
+        self.mode = DuplexSpongeMode::Absorbing {
+            next_absorb_index: 0,
+        };
+        Ok(())
+    }
+
+    /// Returns the element the next `squeeze_field_elements` call would squeeze first, without
+    /// advancing the sponge.
+    ///
+    /// This squeezes a clone of the sponge, so any permutation it needs is synthesized in the
+    /// constraint system, and synthesized again by the actual squeeze.
+    pub fn peek_field_element(&self) -> Result<FpVar<F>, SynthesisError> {
+        Ok(self.clone().squeeze_field_elements(1)?.remove(0))
+    }
+
+    /// Squeezes `num_elements` elements like `squeeze_field_elements`, and also returns a digest
+    /// of the state after the squeeze, to link this transcript to another proof.
+    ///
+    /// The digest is the first rate element after permuting a copy of the whole state, including
+    /// the capacity, with one added to its first element. The sponge itself is left as the squeeze
+    /// left it, and the added one keeps the digest distinct from the elements it squeezes next.
+    /// Two transcripts with the same digest have the same state, so a proof that recomputes the
+    /// digest continues the same transcript.
     #[tracing::instrument(target = "r1cs", skip(self))]
+    pub fn squeeze_and_commit(
+        &mut self,
+        num_elements: usize,
+    ) -> Result<(Vec<FpVar<F>>, FpVar<F>), SynthesisError> {
+        let squeezed = self.squeeze_field_elements(num_elements)?;
+
+        let mut state = self.state.clone();
+        state[0] += F::one();
+        Self::permute_state(&self.parameters, &mut state)?;
+        let digest = state.swap_remove(self.parameters.capacity);
+
+        Ok((squeezed, digest))
+    }
+
+    /// Applies the `10*1` multi-rate padding of [`PoseidonSponge::finalize_absorb`] to the
+    /// absorbed input. The padding is made of constants, so it costs no constraints besides
+    /// the permutations it may trigger.
+    pub fn finalize_absorb(&mut self) -> Result<(), SynthesisError> {
+        let padding: Vec<_> = multirate_padding::<F>(self.parameters.rate, &self.mode)
+            .into_iter()
+            .map(FpVar::Constant)
+            .collect();
+        self.absorb(&padding)
+    }
+
+    /// Returns the inputs passed to `absorb_public` and `absorb_private` so far, in order.
+    #[cfg(feature = "audit")]
+    pub fn audit_log(&self) -> &[AbsorbRecord<F>] {
+        &self.audit_log
+    }
+
+    /// Summarizes the mode, the rate and the number of permutations of the sponge, e.g.
+    /// `absorbing at rate index 1 of 2, permutation count 0`.
+    ///
+    /// Comparing the descriptions of the prover's and the verifier's sponges at the same point
+    /// of a protocol is a quick way to locate where their transcripts diverge.
+    pub fn describe(&self) -> String {
+        format!(
+            "{} of {}, permutation count {}",
+            self.mode, self.parameters.rate, self.permutation_count
+        )
+    }
+
+    /// Returns a fingerprint of the value of the state after each permutation so far.
+    ///
+    /// Each fingerprint is a fixed linear combination of the state elements, so two states
+    /// that differ are very unlikely to share it, but it is not a cryptographic hash. Nothing is
+    /// recorded when the state has no assigned value, e.g. while generating parameters.
+    #[cfg(feature = "debug")]
+    pub fn fingerprint(&self) -> &[F] {
+        &self.fingerprint
+    }
+
+    /// Returns the index of the first permutation after which the states of `self` and `other`
+    /// differ, according to their fingerprints. If one sponge was permuted fewer times and
+    /// agrees with the other so far, this is the number of its permutations. Returns `None` if
+    /// both sponges went through the same states.
+    #[cfg(feature = "debug")]
+    pub fn first_divergence(&self, other: &Self) -> Option<usize> {
+        let common = self.fingerprint.len().min(other.fingerprint.len());
+        (0..common)
+            .find(|&i| self.fingerprint[i] != other.fingerprint[i])
+            .or_else(|| (self.fingerprint.len() != other.fingerprint.len()).then_some(common))
+    }
+
+    /// Returns whether the sponge is absorbing or squeezing, and the index of the next rate
+    /// element it will absorb into or squeeze from.
+    pub fn mode(&self) -> &DuplexSpongeMode {
+        &self.mode
+    }
+
+    /// Returns the current state: the `parameters.capacity` capacity elements followed by the
+    /// `parameters.rate` rate elements.
+    ///
+    /// Absorbed elements are added to the rate before the next permutation, so this can be used to
+    /// constrain the state against other values of the circuit.
+    pub fn state(&self) -> &[FpVar<F>] {
+        &self.state
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(parameters))]
     fn apply_s_box(
-        &self,
+        parameters: &PoseidonParameters<F>,
         state: &mut [FpVar<F>],
         is_full_round: bool,
     ) -> Result<(), SynthesisError> {
         // Full rounds apply the S Box (x^alpha) to every element of state
         if is_full_round {
             for state_item in state.iter_mut() {
-                *state_item = state_item.pow_by_constant(&[self.parameters.alpha])?;
+                *state_item = state_item.pow_by_constant(&[parameters.alpha])?;
             }
         }
         // Partial rounds apply the S Box (x^alpha) to just the first element of state
         else {
-            state[0] = state[0].pow_by_constant(&[self.parameters.alpha])?;
+            state[0] = state[0].pow_by_constant(&[parameters.alpha])?;
         }
 
         Ok(())
     }
 
-    #[tracing::instrument(target = "r1cs", skip(self))]
-    fn apply_ark(&self, state: &mut [FpVar<F>], round_number: usize) -> Result<(), SynthesisError> {
+    #[tracing::instrument(target = "r1cs", skip(parameters))]
+    fn apply_ark(
+        parameters: &PoseidonParameters<F>,
+        state: &mut [FpVar<F>],
+        round_number: usize,
+    ) -> Result<(), SynthesisError> {
         for (i, state_elem) in state.iter_mut().enumerate() {
-            *state_elem += self.parameters.ark[round_number][i];
+            *state_elem += parameters.ark[round_number][i];
         }
         Ok(())
     }
 
-    #[tracing::instrument(target = "r1cs", skip(self))]
-    fn apply_mds(&self, state: &mut [FpVar<F>]) -> Result<(), SynthesisError> {
+    #[tracing::instrument(target = "r1cs", skip(parameters))]
+    fn apply_mds(
+        parameters: &PoseidonParameters<F>,
+        state: &mut [FpVar<F>],
+    ) -> Result<(), SynthesisError> {
         let mut new_state = Vec::new();
         let zero = FpVar::<F>::zero();
         for i in 0..state.len() {
             let mut cur = zero.clone();
             for (j, state_elem) in state.iter().enumerate() {
-                let term = state_elem * self.parameters.mds[i][j];
+                let term = state_elem * parameters.mds[i][j];
                 cur += &term;
             }
             new_state.push(cur);
@@ -79,30 +300,92 @@ impl<F: PrimeField> PoseidonSpongeVar<F> {
         Ok(())
     }
 
-    #[tracing::instrument(target = "r1cs", skip(self))]
-    fn permute(&mut self) -> Result<(), SynthesisError> {
-        let full_rounds_over_2 = self.parameters.full_rounds / 2;
-        let mut state = self.state.clone();
+    // Applies the permutation described by `parameters` to `state`.
+    #[tracing::instrument(target = "r1cs", skip(parameters))]
+    fn permute_state(
+        parameters: &PoseidonParameters<F>,
+        state: &mut [FpVar<F>],
+    ) -> Result<(), SynthesisError> {
+        let full_rounds_over_2 = parameters.full_rounds / 2;
         for i in 0..full_rounds_over_2 {
-            self.apply_ark(&mut state, i)?;
-            self.apply_s_box(&mut state, true)?;
-            self.apply_mds(&mut state)?;
+            Self::apply_ark(parameters, state, i)?;
+            Self::apply_s_box(parameters, state, true)?;
+            Self::apply_mds(parameters, state)?;
         }
-        for i in full_rounds_over_2..(full_rounds_over_2 + self.parameters.partial_rounds) {
-            self.apply_ark(&mut state, i)?;
-            self.apply_s_box(&mut state, false)?;
-            self.apply_mds(&mut state)?;
+        for i in full_rounds_over_2..(full_rounds_over_2 + parameters.partial_rounds) {
+            Self::apply_ark(parameters, state, i)?;
+            Self::apply_s_box(parameters, state, false)?;
+            Self::apply_mds(parameters, state)?;
         }
 
-        for i in (full_rounds_over_2 + self.parameters.partial_rounds)
-            ..(self.parameters.partial_rounds + self.parameters.full_rounds)
+        for i in (full_rounds_over_2 + parameters.partial_rounds)
+            ..(parameters.partial_rounds + parameters.full_rounds)
         {
-            self.apply_ark(&mut state, i)?;
-            self.apply_s_box(&mut state, true)?;
-            self.apply_mds(&mut state)?;
+            Self::apply_ark(parameters, state, i)?;
+            Self::apply_s_box(parameters, state, true)?;
+            Self::apply_mds(parameters, state)?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(self))]
+    fn permute(&mut self) -> Result<(), SynthesisError> {
+        debug_assert_eq!(
+            self.state.len(),
+            self.parameters.rate + self.parameters.capacity,
+            "the state must have `rate + capacity` elements"
+        );
+        #[cfg(feature = "poseidon-trace")]
+        if let Ok(state) = self.state.value() {
+            tracing::trace!(state = %HexState(&state), "permutation input");
+        }
+        Self::permute_state(&self.parameters, &mut self.state)?;
+        #[cfg(feature = "poseidon-trace")]
+        if let Ok(state) = self.state.value() {
+            tracing::trace!(state = %HexState(&state), "permutation output");
         }
+        self.permutation_count += 1;
+        #[cfg(feature = "debug")]
+        if let Ok(state) = self.state.value() {
+            // Any fixed multiplier works, as the fingerprint is only meant for debugging.
+            let multiplier = F::from(0x9e37_79b9_7f4a_7c15u64);
+            let fingerprint = state
+                .iter()
+                .rev()
+                .fold(F::zero(), |acc, elem| acc * multiplier + elem);
+            self.fingerprint.push(fingerprint);
+        }
+        Ok(())
+    }
+
+    // Absorbs field elements, switching to absorbing mode if needed.
+    fn absorb_elements(&mut self, input: &[FpVar<F>]) -> Result<(), SynthesisError> {
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        match self.mode {
+            DuplexSpongeMode::Absorbing { next_absorb_index } => {
+                let mut absorb_index = next_absorb_index;
+                if absorb_index == self.parameters.rate {
+                    self.permute()?;
+                    absorb_index = 0;
+                }
+                self.absorb_internal(absorb_index, input)?;
+            }
+            // See `new_strict` for why this variant is used.
+            DuplexSpongeMode::Squeezing { .. } if self.strict => {
+                return Err(SynthesisError::UnconstrainedVariable);
+            }
+            DuplexSpongeMode::Squeezing {
+                next_squeeze_index: _,
+            } => {
+                self.permute()?;
+                self.absorb_internal(0, input)?;
+            }
+        };
 
-        self.state = state;
         Ok(())
     }
 
@@ -112,12 +395,22 @@ impl<F: PrimeField> PoseidonSpongeVar<F> {
         mut rate_start_index: usize,
         elements: &[FpVar<F>],
     ) -> Result<(), SynthesisError> {
+        debug_assert_eq!(
+            self.state.len(),
+            self.parameters.rate + self.parameters.capacity,
+            "the state must have `rate + capacity` elements"
+        );
+        // A full block is permuted before anything more is absorbed into the rate.
+        if rate_start_index == self.parameters.rate && !elements.is_empty() {
+            self.permute()?;
+            rate_start_index = 0;
+        }
         let mut remaining_elements = elements;
         loop {
             // if we can finish in this call
             if rate_start_index + remaining_elements.len() <= self.parameters.rate {
                 for (i, element) in remaining_elements.iter().enumerate() {
-                    self.state[self.parameters.capacity + i + rate_start_index] += element;
+                    self.absorb_at(i + rate_start_index, element);
                 }
                 self.mode = DuplexSpongeMode::Absorbing {
                     next_absorb_index: rate_start_index + remaining_elements.len(),
@@ -132,7 +425,7 @@ impl<F: PrimeField> PoseidonSpongeVar<F> {
                 .enumerate()
                 .take(num_elements_absorbed)
             {
-                self.state[self.parameters.capacity + i + rate_start_index] += element;
+                self.absorb_at(i + rate_start_index, element);
             }
             self.permute()?;
             // the input elements got truncated by num elements absorbed
@@ -141,6 +434,15 @@ impl<F: PrimeField> PoseidonSpongeVar<F> {
         }
     }
 
+    // Absorbs one element at `rate_index` of the rate, following the absorb mode.
+    fn absorb_at(&mut self, rate_index: usize, element: &FpVar<F>) {
+        let position = &mut self.state[self.parameters.capacity + rate_index];
+        match self.parameters.effective_absorb_mode() {
+            AbsorbMode::Add => *position += element,
+            AbsorbMode::Overwrite => *position = element.clone(),
+        }
+    }
+
     // Squeeze |output| many elements. This does not end in a squeeze
     #[tracing::instrument(target = "r1cs", skip(self))]
     fn squeeze_internal(
@@ -148,10 +450,16 @@ impl<F: PrimeField> PoseidonSpongeVar<F> {
         mut rate_start_index: usize,
         output: &mut [FpVar<F>],
     ) -> Result<(), SynthesisError> {
+        debug_assert_eq!(
+            self.state.len(),
+            self.parameters.rate + self.parameters.capacity,
+            "the state must have `rate + capacity` elements"
+        );
+        let squeeze_rate = self.parameters.effective_squeeze_rate();
         let mut remaining_output = output;
         loop {
             // if we can finish in this call
-            if rate_start_index + remaining_output.len() <= self.parameters.rate {
+            if rate_start_index + remaining_output.len() <= squeeze_rate {
                 remaining_output.clone_from_slice(
                     &self.state[self.parameters.capacity + rate_start_index
                         ..(self.parameters.capacity + remaining_output.len() + rate_start_index)],
@@ -161,17 +469,15 @@ impl<F: PrimeField> PoseidonSpongeVar<F> {
                 };
                 return Ok(());
             }
-            // otherwise squeeze (rate - rate_start_index) elements
-            let num_elements_squeezed = self.parameters.rate - rate_start_index;
+            // otherwise squeeze (squeeze_rate - rate_start_index) elements
+            let num_elements_squeezed = squeeze_rate - rate_start_index;
             remaining_output[..num_elements_squeezed].clone_from_slice(
                 &self.state[self.parameters.capacity + rate_start_index
                     ..(self.parameters.capacity + num_elements_squeezed + rate_start_index)],
             );
 
-            // Unless we are done with squeezing in this call, permute.
-            if remaining_output.len() != self.parameters.rate {
-                self.permute()?;
-            }
+            // More elements are left to squeeze, and they come from the next block.
+            self.permute()?;
             // Repeat with updated output slices and rate start index
             remaining_output = &mut remaining_output[num_elements_squeezed..];
             rate_start_index = 0;
@@ -184,8 +490,10 @@ impl<F: PrimeField> CryptographicSpongeVar<F, PoseidonSponge<F>> for PoseidonSpo
 
     #[tracing::instrument(target = "r1cs", skip(cs))]
     fn new(cs: ConstraintSystemRef<F>, parameters: &PoseidonParameters<F>) -> Self {
-        let zero = FpVar::<F>::zero();
-        let state = vec![zero; parameters.rate + parameters.capacity];
+        if let Err(error) = parameters.check_dimensions() {
+            panic!("invalid Poseidon parameters: {}", error);
+        }
+        let state = initial_state_var(parameters);
         let mode = DuplexSpongeMode::Absorbing {
             next_absorb_index: 0,
         };
@@ -195,6 +503,12 @@ impl<F: PrimeField> CryptographicSpongeVar<F, PoseidonSponge<F>> for PoseidonSpo
             parameters: parameters.clone(),
             state,
             mode,
+            permutation_count: 0,
+            strict: false,
+            #[cfg(feature = "audit")]
+            audit_log: Vec::new(),
+            #[cfg(feature = "debug")]
+            fingerprint: Vec::new(),
         }
     }
 
@@ -206,28 +520,26 @@ impl<F: PrimeField> CryptographicSpongeVar<F, PoseidonSponge<F>> for PoseidonSpo
     #[tracing::instrument(target = "r1cs", skip(self, input))]
     fn absorb(&mut self, input: &impl AbsorbGadget<F>) -> Result<(), SynthesisError> {
         let input = input.to_sponge_field_elements()?;
-        if input.is_empty() {
-            return Ok(());
-        }
+        self.absorb_elements(&input)
+    }
 
-        match self.mode {
-            DuplexSpongeMode::Absorbing { next_absorb_index } => {
-                let mut absorb_index = next_absorb_index;
-                if absorb_index == self.parameters.rate {
-                    self.permute()?;
-                    absorb_index = 0;
-                }
-                self.absorb_internal(absorb_index, input.as_slice())?;
-            }
-            DuplexSpongeMode::Squeezing {
-                next_squeeze_index: _,
-            } => {
-                self.permute()?;
-                self.absorb_internal(0, input.as_slice())?;
-            }
-        };
+    #[tracing::instrument(target = "r1cs", skip(self, input, scratch))]
+    fn absorb_into(
+        &mut self,
+        input: &impl AbsorbGadget<F>,
+        scratch: &mut Vec<FpVar<F>>,
+    ) -> Result<(), SynthesisError> {
+        scratch.clear();
+        input.to_sponge_field_elements_in(scratch)?;
+        self.absorb_elements(scratch)
+    }
 
-        Ok(())
+    #[cfg(feature = "audit")]
+    fn record_absorb(&mut self, visibility: Visibility, elements: &[FpVar<F>]) {
+        self.audit_log.push(AbsorbRecord {
+            visibility,
+            elements: elements.to_vec(),
+        });
     }
 
     #[tracing::instrument(target = "r1cs", skip(self))]
@@ -269,39 +581,272 @@ impl<F: PrimeField> CryptographicSpongeVar<F, PoseidonSponge<F>> for PoseidonSpo
     ) -> Result<Vec<FpVar<F>>, SynthesisError> {
         let zero = FpVar::zero();
         let mut squeezed_elems = vec![zero; num_elements];
+        self.squeeze_field_elements_into(&mut squeezed_elems)?;
+
+        Ok(squeezed_elems)
+    }
+
+    #[tracing::instrument(target = "r1cs", skip(self, out))]
+    fn squeeze_field_elements_into(&mut self, out: &mut [FpVar<F>]) -> Result<(), SynthesisError> {
+        // An empty squeeze must not permute the state or switch the mode.
+        if out.is_empty() {
+            return Ok(());
+        }
+
         match self.mode {
             DuplexSpongeMode::Absorbing {
                 next_absorb_index: _,
             } => {
                 self.permute()?;
-                self.squeeze_internal(0, &mut squeezed_elems)?;
+                self.squeeze_internal(0, out)?;
             }
             DuplexSpongeMode::Squeezing { next_squeeze_index } => {
                 let mut squeeze_index = next_squeeze_index;
-                if squeeze_index == self.parameters.rate {
+                if squeeze_index == self.parameters.effective_squeeze_rate() {
                     self.permute()?;
                     squeeze_index = 0;
                 }
-                self.squeeze_internal(squeeze_index, &mut squeezed_elems)?;
+                self.squeeze_internal(squeeze_index, out)?;
             }
         };
 
-        Ok(squeezed_elems)
+        Ok(())
+    }
+}
+
+/// Compresses `inputs` into a single element.
+///
+/// The output equals what a freshly initialized sponge squeezes after absorbing `inputs`, but
+/// is computed with a single permutation of a local state instead of a full sponge. Inputs of
+/// different lengths are not told apart, e.g. `[a]` and `[a, 0]` compress to the same element,
+/// so callers should use a fixed number of inputs.
+/// `inputs` must have at most `parameters.rate` elements.
+#[tracing::instrument(target = "r1cs", skip(parameters))]
+pub fn compress<F: PrimeField>(
+    parameters: &PoseidonParameters<F>,
+    inputs: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    assert!(
+        inputs.len() <= parameters.rate,
+        "{} inputs do not fit in a rate of {}",
+        inputs.len(),
+        parameters.rate
+    );
+
+    let initial_state = initial_state_var(parameters);
+    let mut state = initial_state.clone();
+    compress_in(parameters, &initial_state, &mut state, inputs)
+}
+
+// The initial state of `parameters` as constants.
+fn initial_state_var<F: PrimeField>(parameters: &PoseidonParameters<F>) -> Vec<FpVar<F>> {
+    parameters
+        .initial_state()
+        .into_iter()
+        .map(FpVar::Constant)
+        .collect()
+}
+
+// Compresses `inputs` as `compress` does, resetting `state` to `initial_state` first. This is
+// the only implementation of the compression, so callers that reuse a state buffer, like
+// `hash_many_pairs`, cannot drift from `compress`.
+fn compress_in<F: PrimeField>(
+    parameters: &PoseidonParameters<F>,
+    initial_state: &[FpVar<F>],
+    state: &mut [FpVar<F>],
+    inputs: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    state.clone_from_slice(initial_state);
+    state[parameters.capacity..parameters.capacity + inputs.len()].clone_from_slice(inputs);
+    PoseidonSpongeVar::permute_state(parameters, state)?;
+    Ok(state[parameters.capacity].clone())
+}
+
+/// Compresses `left` and `right` into a single element.
+///
+/// This is [`compress`] on two inputs, which makes it cheap to use for every node of a Merkle
+/// tree. The rate of `parameters` must be at least 2.
+#[tracing::instrument(target = "r1cs", skip(parameters))]
+pub fn two_to_one<F: PrimeField>(
+    parameters: &PoseidonParameters<F>,
+    left: &FpVar<F>,
+    right: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    assert!(
+        parameters.rate >= 2,
+        "two-to-one compression requires a rate of at least 2"
+    );
+
+    compress(parameters, &[left.clone(), right.clone()])
+}
+
+/// Compresses every pair of `pairs` with [`two_to_one`], in order.
+///
+/// The outputs and the constraints are the same as calling `two_to_one` on each pair. The pairs
+/// are independent of each other, so callers that generate witnesses in parallel can split
+/// `pairs` into chunks freely.
+#[tracing::instrument(target = "r1cs", skip(parameters, pairs))]
+pub fn hash_many_pairs<F: PrimeField>(
+    parameters: &PoseidonParameters<F>,
+    pairs: &[(FpVar<F>, FpVar<F>)],
+) -> Result<Vec<FpVar<F>>, SynthesisError> {
+    assert!(
+        parameters.rate >= 2,
+        "two-to-one compression requires a rate of at least 2"
+    );
+
+    let initial_state = initial_state_var(parameters);
+    let mut outputs = Vec::with_capacity(pairs.len());
+    let mut state = initial_state.clone();
+    for (left, right) in pairs {
+        outputs.push(compress_in(
+            parameters,
+            &initial_state,
+            &mut state,
+            &[left.clone(), right.clone()],
+        )?);
+    }
+
+    Ok(outputs)
+}
+
+/// Runs the gadget on constant `inputs` without a constraint system, and returns the values of
+/// the first `num_outputs` elements it squeezes.
+///
+/// The sponge is created with `ConstraintSystemRef::None`, so every operation is
+/// constant-folded. The outputs equal those of a native sponge that absorbs `inputs`, which makes
+/// this handy to prototype gadget code against native values.
+pub fn simulate<F: PrimeField>(
+    parameters: &PoseidonParameters<F>,
+    inputs: &[F],
+    num_outputs: usize,
+) -> Vec<F> {
+    let inputs: Vec<_> = inputs.iter().copied().map(FpVar::Constant).collect();
+    let mut sponge = PoseidonSpongeVar::new(ConstraintSystemRef::None, parameters);
+    sponge
+        .absorb(&inputs)
+        .and_then(|_| sponge.squeeze_field_elements(num_outputs))
+        .and_then(|outputs| outputs.value())
+        .expect("operations on constants cannot fail")
+}
+
+#[derive(Clone)]
+/// The gadget for [`PoseidonMac`](crate::poseidon::PoseidonMac).
+///
+/// Tags are computed the same way, by absorbing the key and then the message into a fresh
+/// sponge and squeezing one element.
+pub struct PoseidonMacVar<F: PrimeField> {
+    cs: ConstraintSystemRef<F>,
+    parameters: PoseidonParameters<F>,
+    key: FpVar<F>,
+}
+
+impl<F: PrimeField> PoseidonMacVar<F> {
+    /// Creates a MAC with the secret `key`, which is usually allocated as a witness.
+    pub fn new(
+        cs: ConstraintSystemRef<F>,
+        parameters: &PoseidonParameters<F>,
+        key: FpVar<F>,
+    ) -> Self {
+        Self {
+            cs,
+            parameters: parameters.clone(),
+            key,
+        }
+    }
+
+    /// Returns the tag of `message`.
+    #[tracing::instrument(target = "r1cs", skip(self, message))]
+    pub fn mac(&self, message: &impl AbsorbGadget<F>) -> Result<FpVar<F>, SynthesisError> {
+        let mut sponge = PoseidonSpongeVar::new(self.cs.clone(), &self.parameters);
+        sponge.absorb(&self.key)?;
+        sponge.absorb(message)?;
+        Ok(sponge.squeeze_field_elements(1)?.remove(0))
+    }
+
+    /// Returns whether `tag` is the tag of `message`. Enforce the result to be true to check the
+    /// tag in the circuit.
+    #[tracing::instrument(target = "r1cs", skip(self, message, tag))]
+    pub fn verify(
+        &self,
+        message: &impl AbsorbGadget<F>,
+        tag: &FpVar<F>,
+    ) -> Result<Boolean<F>, SynthesisError> {
+        self.mac(message)?.is_eq(tag)
+    }
+}
+
+#[derive(Clone)]
+/// The gadget for [`PoseidonPrf`](crate::poseidon::PoseidonPrf).
+///
+/// Outputs are computed the same way, by tagging the capacity of a fresh sponge with
+/// [`PoseidonSpongeVar::absorb_into_capacity`], absorbing the key and then the input, and
+/// squeezing the output.
+pub struct PoseidonPrfVar<F: PrimeField> {
+    cs: ConstraintSystemRef<F>,
+    parameters: PoseidonParameters<F>,
+    key: FpVar<F>,
+}
+
+impl<F: PrimeField> PoseidonPrfVar<F> {
+    /// Creates a PRF with the secret `key`, which is usually allocated as a witness.
+    ///
+    /// Panics if `parameters` has no capacity to tag.
+    pub fn new(
+        cs: ConstraintSystemRef<F>,
+        parameters: &PoseidonParameters<F>,
+        key: FpVar<F>,
+    ) -> Self {
+        assert!(
+            parameters.capacity > 0,
+            "the PRF is domain-separated through the capacity"
+        );
+        Self {
+            cs,
+            parameters: parameters.clone(),
+            key,
+        }
+    }
+
+    /// Returns the first `output_len` output elements for `input`.
+    #[tracing::instrument(target = "r1cs", skip(self, input))]
+    pub fn prf(
+        &self,
+        input: &impl AbsorbGadget<F>,
+        output_len: usize,
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut sponge = PoseidonSpongeVar::new(self.cs.clone(), &self.parameters);
+        sponge.absorb_into_capacity(&[FpVar::one()]);
+        sponge.absorb(&self.key)?;
+        sponge.absorb(input)?;
+        sponge.squeeze_field_elements(output_len)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::constraints::CryptographicSpongeVar;
-    use crate::poseidon::constraints::PoseidonSpongeVar;
+    #[cfg(feature = "audit")]
+    use crate::constraints::Visibility;
+    use crate::constraints::{AbsorbGadget, CryptographicSpongeVar};
+    use crate::poseidon::constraints::{
+        compress, hash_many_pairs, simulate, two_to_one, PoseidonMacVar, PoseidonPrfVar,
+        PoseidonSpongeVar,
+    };
     use crate::poseidon::tests::poseidon_parameters_for_test;
-    use crate::poseidon::PoseidonSponge;
-    use crate::{CryptographicSponge, FieldBasedCryptographicSponge};
-    use ark_ff::UniformRand;
+    use crate::poseidon::{
+        find_poseidon_ark_and_mds, AbsorbMode, PoseidonMac, PoseidonParameters, PoseidonPrf,
+        PoseidonSponge,
+    };
+    use crate::{
+        CryptographicSponge, DuplexSpongeMode, Endianness, FieldBasedCryptographicSponge,
+        FieldElementSize,
+    };
+    use ark_ff::{BigInteger, Field, FpParameters, One, PrimeField, UniformRand, Zero};
     use ark_r1cs_std::fields::fp::FpVar;
     use ark_r1cs_std::prelude::*;
-    use ark_relations::r1cs::ConstraintSystem;
+    use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError};
     use ark_relations::*;
+    use ark_std::rand::Rng;
     use ark_std::test_rng;
     use ark_test_curves::bls12_381::Fr;
 
@@ -345,4 +890,1412 @@ mod tests {
         assert_eq!(squeeze2.value().unwrap(), squeeze1);
         assert!(cs.is_satisfied().unwrap());
     }
+
+    #[test]
+    fn permutation_count_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+
+        let sponge_params = poseidon_parameters_for_test();
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        assert_eq!(constraint_sponge.permutation_count(), 0);
+
+        let absorb: Vec<_> = (0..5)
+            .map(|_| FpVar::new_input(ns!(cs, "absorb"), || Ok(Fr::rand(&mut rng))).unwrap())
+            .collect();
+
+        // With rate 2, absorbing 5 elements fills the rate twice.
+        constraint_sponge.absorb(&absorb).unwrap();
+        assert_eq!(constraint_sponge.permutation_count(), 2);
+
+        // Switching to squeezing permutes once, and 3 elements need one more block.
+        constraint_sponge.squeeze_field_elements(3).unwrap();
+        assert_eq!(constraint_sponge.permutation_count(), 4);
+
+        // Switching back to absorbing permutes once more.
+        constraint_sponge.absorb(&absorb[0]).unwrap();
+        assert_eq!(constraint_sponge.permutation_count(), 5);
+    }
+
+    #[test]
+    fn squeeze_rate_test() {
+        let cs = ConstraintSystem::new_ref();
+        let mut sponge_params = poseidon_parameters_for_test();
+        sponge_params.squeeze_rate = Some(1);
+        let input = Fr::from(3u64);
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&input);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        constraint_sponge
+            .absorb(&FpVar::new_witness(ns!(cs, "input"), || Ok(input)).unwrap())
+            .unwrap();
+
+        // Switching to squeezing permutes once, and each further element permutes again.
+        let squeezed = constraint_sponge.squeeze_field_elements(3).unwrap();
+        assert_eq!(constraint_sponge.permutation_count(), 3);
+        assert_eq!(
+            squeezed.value().unwrap(),
+            native_sponge.squeeze_native_field_elements(3)
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn squeeze_rate_below_rate_3_test() {
+        let cs = ConstraintSystem::new_ref();
+        let (ark, mds) = find_poseidon_ark_and_mds::<Fr>(255, 3, 8, 31, 0);
+        let full_params = PoseidonParameters::new(8, 31, 5, mds, ark, 3, 1);
+        let mut narrow_params = full_params.clone();
+        narrow_params.squeeze_rate = Some(1);
+        let input = FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::from(1u64))).unwrap();
+
+        let squeeze = |sponge_params: &PoseidonParameters<Fr>| {
+            let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), sponge_params);
+            sponge.absorb(&input).unwrap();
+            let squeezed = sponge.squeeze_field_elements(3).unwrap().value().unwrap();
+            (squeezed, sponge.permutation_count())
+        };
+        let (full, full_permutations) = squeeze(&full_params);
+        let (narrow, narrow_permutations) = squeeze(&narrow_params);
+
+        // The full rate squeezes all 3 elements from one permutation, while a squeeze rate of 1
+        // permutes for every element. Only the first element, read before any extra
+        // permutation, is the same.
+        assert_eq!(full_permutations, 1);
+        assert_eq!(narrow_permutations, 3);
+        assert_eq!(narrow[0], full[0]);
+        assert_ne!(narrow[1..], full[1..]);
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&narrow_params);
+        native_sponge.absorb(&Fr::from(1u64));
+        assert_eq!(narrow, native_sponge.squeeze_native_field_elements(3));
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn initial_state_iv_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let mut sponge_params = poseidon_parameters_for_test();
+        sponge_params.initial_state_iv = Some(vec![Fr::from(5u64)]);
+        let inputs = [Fr::rand(&mut rng), Fr::rand(&mut rng)];
+        let input_vars: Vec<_> = inputs
+            .iter()
+            .map(|input| FpVar::new_witness(ns!(cs, "input"), || Ok(*input)).unwrap())
+            .collect();
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&inputs.to_vec());
+        let expected = native_sponge.squeeze_native_field_elements(2);
+
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        constraint_sponge.absorb(&input_vars).unwrap();
+        assert_eq!(
+            constraint_sponge
+                .squeeze_field_elements(2)
+                .unwrap()
+                .value()
+                .unwrap(),
+            expected
+        );
+
+        // The compression functions start from the IV too, as a fresh sponge does.
+        let (left, right) = (input_vars[0].clone(), input_vars[1].clone());
+        assert_eq!(
+            two_to_one(&sponge_params, &left, &right)
+                .unwrap()
+                .value()
+                .unwrap(),
+            expected[0]
+        );
+        assert_eq!(
+            hash_many_pairs(&sponge_params, &vec![(left.clone(), right.clone()); 2])
+                .unwrap()
+                .value()
+                .unwrap(),
+            vec![expected[0]; 2]
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn absorb_mode_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let mut sponge_params = poseidon_parameters_for_test();
+        sponge_params.absorb_mode = Some(AbsorbMode::Overwrite);
+        let inputs: Vec<_> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+        let inputs_var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(inputs.clone())).unwrap();
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&inputs);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        constraint_sponge.absorb(&inputs_var).unwrap();
+        assert_eq!(
+            constraint_sponge.state.value().unwrap(),
+            native_sponge.state
+        );
+        assert_eq!(
+            constraint_sponge
+                .squeeze_field_elements(2)
+                .unwrap()
+                .value()
+                .unwrap(),
+            native_sponge.squeeze_native_field_elements(2)
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn two_to_one_merkle_root_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+
+        let native_two_to_one = |left: Fr, right: Fr| {
+            let mut sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+            sponge.absorb(&vec![left, right]);
+            sponge.squeeze_native_field_elements(1)[0]
+        };
+
+        let leaves: Vec<_> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let leaves_var: Vec<_> = leaves
+            .iter()
+            .map(|leaf| FpVar::new_witness(ns!(cs, "leaf"), || Ok(*leaf)).unwrap())
+            .collect();
+
+        let native_root = native_two_to_one(
+            native_two_to_one(leaves[0], leaves[1]),
+            native_two_to_one(leaves[2], leaves[3]),
+        );
+
+        let left = two_to_one(&sponge_params, &leaves_var[0], &leaves_var[1]).unwrap();
+        let right = two_to_one(&sponge_params, &leaves_var[2], &leaves_var[3]).unwrap();
+        let root = two_to_one(&sponge_params, &left, &right).unwrap();
+
+        assert_eq!(root.value().unwrap(), native_root);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn squeeze_with_sizes_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+
+        let absorb: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let absorb_var: Vec<_> = absorb
+            .iter()
+            .map(|v| FpVar::new_witness(ns!(cs, "absorb"), || Ok(*v)).unwrap())
+            .collect();
+
+        let sponge_params = poseidon_parameters_for_test();
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        native_sponge.absorb(&absorb);
+        constraint_sponge.absorb(&absorb_var).unwrap();
+
+        let sizes = [
+            FieldElementSize::Full,
+            FieldElementSize::Truncated(128),
+            FieldElementSize::Full,
+        ];
+        let native_elems = native_sponge.squeeze_native_field_elements_with_sizes(&sizes);
+        let elems = constraint_sponge
+            .squeeze_field_elements_with_sizes(&sizes)
+            .unwrap();
+        assert_eq!(elems.value().unwrap(), native_elems);
+
+        let bound = Fr::from(2u64).pow([128]);
+        assert!(native_elems[1].into_repr() < bound.into_repr());
+        assert!(cs.is_satisfied().unwrap());
+
+        // A witness that claims to equal the truncated element but exceeds its width cannot
+        // satisfy the constraints.
+        let claimed =
+            FpVar::new_witness(ns!(cs, "claimed"), || Ok(native_elems[1] + bound)).unwrap();
+        claimed.enforce_equal(&elems[1]).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn absorb_with_size_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let size = FieldElementSize::Truncated(64);
+
+        let small = Fr::from(u64::MAX);
+        let small_var = FpVar::new_witness(ns!(cs, "small"), || Ok(small)).unwrap();
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        native_sponge.absorb(&small);
+        constraint_sponge
+            .absorb_field_element_with_size(&small_var, size)
+            .unwrap();
+        assert_eq!(
+            constraint_sponge
+                .squeeze_field_elements(1)
+                .unwrap()
+                .value()
+                .unwrap(),
+            native_sponge.squeeze_native_field_elements(1)
+        );
+        assert!(cs.is_satisfied().unwrap());
+
+        let large_var = FpVar::new_witness(ns!(cs, "large"), || Ok(small + Fr::one())).unwrap();
+        constraint_sponge
+            .absorb_field_element_with_size(&large_var, size)
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn absorb_padded_test() {
+        let mut rng = test_rng();
+        let sponge_params = poseidon_parameters_for_test();
+
+        let mut num_constraints = Vec::new();
+        for len in [1, 4, 6] {
+            let cs = ConstraintSystem::new_ref();
+            let input: Vec<_> = (0..len)
+                .map(|_| FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::rand(&mut rng))).unwrap())
+                .collect();
+
+            let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            sponge.absorb_padded(&input, 6).unwrap();
+            sponge.squeeze_field_elements(1).unwrap();
+
+            assert!(cs.is_satisfied().unwrap());
+            num_constraints.push(cs.num_constraints());
+        }
+        assert!(num_constraints.iter().all(|n| *n == num_constraints[0]));
+
+        // The length prefix separates an input from the same input followed by zeros.
+        let cs = ConstraintSystem::new_ref();
+        let short = vec![FpVar::new_witness(ns!(cs, "short"), || Ok(Fr::one())).unwrap()];
+        let long = vec![short[0].clone(), FpVar::zero()];
+        let mut sponge1 = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        let mut sponge2 = PoseidonSpongeVar::<Fr>::new(cs, &sponge_params);
+        sponge1.absorb_padded(&short, 2).unwrap();
+        sponge2.absorb_padded(&long, 2).unwrap();
+        assert_ne!(
+            sponge1.squeeze_field_elements(1).unwrap().value().unwrap(),
+            sponge2.squeeze_field_elements(1).unwrap().value().unwrap()
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "the state must have `rate + capacity` elements")]
+    fn mismatched_state_length_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs, &sponge_params);
+        sponge.state.pop();
+
+        let _ = sponge.squeeze_field_elements(1);
+    }
+
+    #[test]
+    fn empty_squeeze_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        native_sponge.absorb(&Fr::one());
+        constraint_sponge
+            .absorb(&FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::one())).unwrap())
+            .unwrap();
+        let state = constraint_sponge.state.value().unwrap();
+        let num_constraints = cs.num_constraints();
+
+        assert!(constraint_sponge
+            .squeeze_field_elements(0)
+            .unwrap()
+            .is_empty());
+        assert!(constraint_sponge.squeeze_bytes(0).unwrap().is_empty());
+        assert!(constraint_sponge.squeeze_bits(0).unwrap().is_empty());
+        assert_eq!(constraint_sponge.state.value().unwrap(), state);
+        assert!(matches!(
+            constraint_sponge.mode,
+            DuplexSpongeMode::Absorbing {
+                next_absorb_index: 1
+            }
+        ));
+        assert_eq!(cs.num_constraints(), num_constraints);
+
+        assert_eq!(
+            constraint_sponge
+                .squeeze_field_elements(3)
+                .unwrap()
+                .value()
+                .unwrap(),
+            native_sponge.squeeze_native_field_elements(3)
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn mode_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        assert_eq!(sponge_params.rate, 2);
+
+        sponge
+            .absorb(&FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::one())).unwrap())
+            .unwrap();
+        assert!(matches!(
+            sponge.mode(),
+            DuplexSpongeMode::Absorbing {
+                next_absorb_index: 1
+            }
+        ));
+
+        sponge.squeeze_field_elements(1).unwrap();
+        assert!(matches!(
+            sponge.mode(),
+            DuplexSpongeMode::Squeezing {
+                next_squeeze_index: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn describe_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        sponge.absorb(&FpVar::Constant(Fr::one())).unwrap();
+        assert_eq!(sponge.mode().to_string(), "absorbing at rate index 1");
+        assert_eq!(
+            sponge.describe(),
+            "absorbing at rate index 1 of 2, permutation count 0"
+        );
+
+        sponge.squeeze_field_elements(1).unwrap();
+        assert_eq!(sponge.mode().to_string(), "squeezing at rate index 1");
+        assert_eq!(
+            sponge.describe(),
+            "squeezing at rate index 1 of 2, permutation count 1"
+        );
+    }
+
+    #[test]
+    fn hash_many_pairs_test() {
+        let mut rng = test_rng();
+        let sponge_params = poseidon_parameters_for_test();
+
+        let inputs: Vec<_> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+        let allocate = |cs: ConstraintSystemRef<Fr>| {
+            inputs
+                .chunks(2)
+                .map(|pair| {
+                    let left = FpVar::new_witness(ns!(cs, "left"), || Ok(pair[0])).unwrap();
+                    let right = FpVar::new_witness(ns!(cs, "right"), || Ok(pair[1])).unwrap();
+                    (left, right)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let single_cs = ConstraintSystem::new_ref();
+        let expected: Vec<_> = allocate(single_cs.clone())
+            .iter()
+            .map(|(left, right)| two_to_one(&sponge_params, left, right).unwrap())
+            .collect();
+
+        let batch_cs = ConstraintSystem::new_ref();
+        let pairs = allocate(batch_cs.clone());
+        let outputs = hash_many_pairs(&sponge_params, &pairs).unwrap();
+
+        assert_eq!(outputs.value().unwrap(), expected.value().unwrap());
+        assert_eq!(batch_cs.num_constraints(), single_cs.num_constraints());
+        assert!(batch_cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn linear_layer_is_free_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let width = sponge_params.rate + sponge_params.capacity;
+
+        let mut state: Vec<_> = (0..width)
+            .map(|_| FpVar::new_witness(ns!(cs, "state"), || Ok(Fr::rand(&mut rng))).unwrap())
+            .collect();
+        PoseidonSpongeVar::permute_state(&sponge_params, &mut state).unwrap();
+
+        // The round constants and the MDS matrix only form linear combinations, which R1CS
+        // gets for free, so only the S-boxes cost constraints: `x^17` takes four squarings
+        // and one multiplication, on every element in full rounds and on one in partial rounds.
+        let num_s_boxes = sponge_params.full_rounds * width + sponge_params.partial_rounds;
+        assert_eq!(cs.num_constraints(), num_s_boxes * 5);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn helpers_consistency_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+
+        // Squeezes one element after `absorb`, off-circuit and in the circuit.
+        let native = |absorb: &dyn Fn(&mut PoseidonSponge<Fr>)| {
+            let mut sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+            absorb(&mut sponge);
+            sponge.squeeze_native_field_elements(1)
+        };
+        let gadget = |absorb: &dyn Fn(&mut PoseidonSpongeVar<Fr>) -> Result<(), SynthesisError>| {
+            let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            absorb(&mut sponge).unwrap();
+            sponge.squeeze_field_elements(1).unwrap().value().unwrap()
+        };
+
+        // The label is made of constants, so it costs no constraints.
+        let label = gadget(&|sponge| sponge.absorb_str("abc"));
+        assert_eq!(cs.num_constraints(), 0);
+
+        let input = Fr::rand(&mut rng);
+        let randomness = Fr::rand(&mut rng);
+        let input_var = FpVar::new_witness(ns!(cs, "input"), || Ok(input)).unwrap();
+        let randomness_var = FpVar::new_witness(ns!(cs, "randomness"), || Ok(randomness)).unwrap();
+        let private = vec![input_var.clone(), randomness_var.clone()];
+        let bytes: Vec<u8> = (0..40).collect();
+        let bytes_var = UInt8::new_witness_vec(ns!(cs, "bytes"), &bytes).unwrap();
+        let bit = Boolean::new_witness(ns!(cs, "bit"), || Ok(true)).unwrap();
+        let items: Vec<&dyn AbsorbGadget<Fr>> = vec![&input_var, &bytes_var, &bit, &input_var];
+
+        let mut absorbed = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        absorbed.absorb(&input_var).unwrap();
+        let squeezed = absorbed.clone().squeeze_field_elements(10).unwrap();
+        let array: [FpVar<Fr>; 4] = absorbed.clone().squeeze_array().unwrap();
+        let mut buffer = vec![FpVar::zero(); 5];
+        let mut squeezed_into = Vec::new();
+        for _ in 0..2 {
+            absorbed.squeeze_field_elements_into(&mut buffer).unwrap();
+            squeezed_into.extend(buffer.value().unwrap());
+        }
+
+        let cases = vec![
+            (
+                "absorb_str",
+                label,
+                native(&|sponge| sponge.absorb(&"abc".as_bytes())),
+            ),
+            (
+                "absorb_digest",
+                gadget(&|sponge| sponge.absorb_digest(&input_var)),
+                native(&|sponge| sponge.absorb(&input)),
+            ),
+            (
+                "absorb_opening",
+                gadget(&|sponge| sponge.absorb_opening(&input_var, &randomness_var)),
+                native(&|sponge| sponge.absorb(&vec![input, randomness])),
+            ),
+            (
+                "absorb_labeled",
+                gadget(&|sponge| sponge.absorb_labeled(b"commitment", &input_var)),
+                native(&|sponge| {
+                    sponge.absorb(&b"commitment".as_ref());
+                    sponge.absorb(&input);
+                }),
+            ),
+            (
+                "absorb_public and absorb_private",
+                gadget(&|sponge| {
+                    sponge.absorb_public(&input_var)?;
+                    sponge.absorb_private(&private)
+                }),
+                native(&|sponge| {
+                    sponge.absorb(&input);
+                    sponge.absorb(&vec![input, randomness]);
+                }),
+            ),
+            (
+                "absorb_message",
+                gadget(&|sponge| sponge.absorb_message(&bytes_var)),
+                native(&|sponge| sponge.absorb_message(&bytes)),
+            ),
+            (
+                "absorb_bytes_packed",
+                gadget(&|sponge| sponge.absorb_bytes_packed(&bytes_var, 31)),
+                native(&|sponge| {
+                    // Off-circuit, 31-byte chunks are read as little-endian integers.
+                    let packed: Vec<_> =
+                        bytes.chunks(31).map(Fr::from_le_bytes_mod_order).collect();
+                    sponge.absorb(&packed);
+                }),
+            ),
+            (
+                "absorb_bytes_unpacked",
+                gadget(&|sponge| sponge.absorb_bytes_unpacked(&bytes_var)),
+                native(&|sponge| bytes.iter().for_each(|byte| sponge.absorb(byte))),
+            ),
+            (
+                "absorb_many",
+                gadget(&|sponge| sponge.absorb_many(&items)),
+                gadget(&|sponge| {
+                    sponge.absorb(&input_var)?;
+                    sponge.absorb(&bytes_var)?;
+                    sponge.absorb(&bit)?;
+                    sponge.absorb(&input_var)
+                }),
+            ),
+            (
+                "squeeze_array",
+                array.value().unwrap().to_vec(),
+                squeezed[..4].value().unwrap(),
+            ),
+            (
+                "squeeze_field_elements_into",
+                squeezed_into,
+                squeezed.value().unwrap(),
+            ),
+        ];
+        for (name, actual, expected) in cases {
+            assert_eq!(actual, expected, "{}", name);
+        }
+
+        // Labels separate otherwise equal inputs, and unpacked bytes are absorbed one element
+        // per byte rather than as a packed `&[UInt8]`.
+        assert_ne!(
+            gadget(&|sponge| sponge.absorb_labeled(b"commitment", &input_var)),
+            gadget(&|sponge| sponge.absorb_labeled(b"evaluation", &input_var))
+        );
+        assert_ne!(
+            gadget(&|sponge| sponge.absorb_bytes_unpacked(&bytes_var)),
+            gadget(&|sponge| sponge.absorb(&bytes_var))
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[cfg(feature = "audit")]
+    #[test]
+    fn audit_log_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let public = FpVar::new_input(ns!(cs, "public"), || Ok(Fr::from(1u64))).unwrap();
+        let private = vec![
+            FpVar::new_witness(ns!(cs, "private"), || Ok(Fr::from(2u64))).unwrap(),
+            FpVar::new_witness(ns!(cs, "private"), || Ok(Fr::from(3u64))).unwrap(),
+        ];
+
+        let mut audited = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        audited.absorb_public(&public).unwrap();
+        audited.absorb_private(&private).unwrap();
+        let log = audited.audit_log();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].visibility, Visibility::Public);
+        assert_eq!(log[0].elements.value().unwrap(), vec![Fr::from(1u64)]);
+        assert_eq!(log[1].visibility, Visibility::Private);
+        assert_eq!(log[1].elements.len(), 2);
+    }
+
+    #[test]
+    fn compress_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+
+        for len in 0..=sponge_params.rate {
+            let inputs: Vec<_> = (0..len).map(|_| Fr::rand(&mut rng)).collect();
+            let inputs_var: Vec<_> = inputs
+                .iter()
+                .map(|input| FpVar::new_witness(ns!(cs, "input"), || Ok(*input)).unwrap())
+                .collect();
+
+            let mut sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+            sponge.absorb(&inputs);
+            assert_eq!(
+                compress(&sponge_params, &inputs_var)
+                    .unwrap()
+                    .value()
+                    .unwrap(),
+                sponge.squeeze_native_field_elements(1)[0]
+            );
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn absorb_map_sorted_by_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+
+        let entries: Vec<_> = (0..4u8)
+            .map(|label| {
+                let value = FpVar::new_witness(ns!(cs, "value"), || Ok(Fr::rand(&mut rng)));
+                (UInt8::constant(label), value.unwrap())
+            })
+            .collect();
+        let label = |entry: &(UInt8<Fr>, FpVar<Fr>)| entry.0.value().unwrap();
+
+        let mut expected = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        expected.absorb(&entries).unwrap();
+        let expected = expected.squeeze_field_elements(1).unwrap().value().unwrap();
+
+        for order in [[3, 1, 0, 2], [2, 3, 1, 0], [0, 1, 2, 3]] {
+            let shuffled = order.iter().map(|i| entries[*i].clone());
+            let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            sponge.absorb_map_sorted_by(shuffled, label).unwrap();
+            assert_eq!(
+                sponge.squeeze_field_elements(1).unwrap().value().unwrap(),
+                expected
+            );
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn absorb_rate_multiples_test() {
+        let mut rng = test_rng();
+        let sponge_params = poseidon_parameters_for_test();
+        let rate = sponge_params.rate;
+
+        for len in [rate, 2 * rate, rate + 1] {
+            let cs = ConstraintSystem::new_ref();
+            let absorb: Vec<_> = (0..len).map(|_| Fr::rand(&mut rng)).collect();
+            let absorb_var: Vec<_> = absorb
+                .iter()
+                .map(|v| FpVar::new_witness(ns!(cs, "absorb"), || Ok(*v)).unwrap())
+                .collect();
+
+            let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+            let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            native_sponge.absorb(&absorb);
+            constraint_sponge.absorb(&absorb_var).unwrap();
+
+            // A full rate is only permuted once more data comes in, so `len` elements take
+            // `(len - 1) / rate` permutations to absorb.
+            assert_eq!(constraint_sponge.permutation_count(), (len - 1) / rate);
+            assert_eq!(
+                constraint_sponge.state.value().unwrap(),
+                native_sponge.state
+            );
+
+            assert_eq!(
+                constraint_sponge
+                    .squeeze_field_elements(3)
+                    .unwrap()
+                    .value()
+                    .unwrap(),
+                native_sponge.squeeze_native_field_elements(3)
+            );
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn constant_folding_test() {
+        let mut rng = test_rng();
+        let sponge_params = poseidon_parameters_for_test();
+        let absorb: Vec<_> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+        let absorb_var: Vec<_> = absorb.iter().map(|v| FpVar::constant(*v)).collect();
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        let mut constraint_sponge =
+            PoseidonSpongeVar::<Fr>::new(ConstraintSystemRef::None, &sponge_params);
+        native_sponge.absorb(&absorb);
+        constraint_sponge.absorb(&absorb_var).unwrap();
+
+        let squeezed = constraint_sponge.squeeze_field_elements(3).unwrap();
+        assert!(squeezed.iter().all(|elem| elem.is_constant()));
+        assert_eq!(
+            squeezed.value().unwrap(),
+            native_sponge.squeeze_native_field_elements(3)
+        );
+        assert_eq!(
+            constraint_sponge
+                .squeeze_bytes(10)
+                .unwrap()
+                .value()
+                .unwrap(),
+            native_sponge.squeeze_bytes(10)
+        );
+        assert_eq!(
+            constraint_sponge.squeeze_bits(10).unwrap().value().unwrap(),
+            native_sponge.squeeze_bits(10)
+        );
+    }
+
+    #[test]
+    fn finalize_absorb_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+
+        for len in 0..5 {
+            let absorb: Vec<_> = (0..len).map(|_| Fr::rand(&mut rng)).collect();
+            let absorb_var: Vec<_> = absorb
+                .iter()
+                .map(|v| FpVar::new_witness(ns!(cs, "absorb"), || Ok(*v)).unwrap())
+                .collect();
+
+            let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+            let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            native_sponge.absorb(&absorb);
+            native_sponge.finalize_absorb();
+            constraint_sponge.absorb(&absorb_var).unwrap();
+            constraint_sponge.finalize_absorb().unwrap();
+
+            assert_eq!(
+                constraint_sponge
+                    .squeeze_field_elements(3)
+                    .unwrap()
+                    .value()
+                    .unwrap(),
+                native_sponge.squeeze_native_field_elements(3)
+            );
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn squeeze_indices_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let input = FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::from(7u64))).unwrap();
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        native_sponge.absorb(&Fr::from(7u64));
+        constraint_sponge.absorb(&input).unwrap();
+
+        for modulus in [1, 5, 8, 1000] {
+            let indices = native_sponge.squeeze_indices(4, modulus);
+            assert!(indices.iter().all(|index| *index < modulus));
+            let expected: Vec<_> = indices.iter().map(|i| Fr::from(*i as u64)).collect();
+            assert_eq!(
+                constraint_sponge
+                    .squeeze_indices(4, modulus)
+                    .unwrap()
+                    .value()
+                    .unwrap(),
+                expected
+            );
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn strict_absorb_after_squeeze_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let input = vec![FpVar::Constant(Fr::from(1u64))];
+
+        let mut strict = PoseidonSpongeVar::<Fr>::new_strict(cs.clone(), &sponge_params);
+        strict.absorb(&input).unwrap();
+        strict.absorb(&input).unwrap();
+        strict.squeeze_field_elements(1).unwrap();
+        assert!(matches!(
+            strict.absorb(&input),
+            Err(SynthesisError::UnconstrainedVariable)
+        ));
+        // Absorbing nothing is still a no-op.
+        strict.absorb(&Vec::<FpVar<Fr>>::new()).unwrap();
+
+        let mut permissive = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        permissive.absorb(&input).unwrap();
+        permissive.squeeze_field_elements(1).unwrap();
+        permissive.absorb(&input).unwrap();
+        permissive.squeeze_field_elements(1).unwrap();
+    }
+
+    #[test]
+    fn partial_absorb_then_squeeze_test() {
+        let cs = ConstraintSystem::new_ref();
+        let (ark, mds) = find_poseidon_ark_and_mds::<Fr>(255, 3, 8, 31, 0);
+        let sponge_params = PoseidonParameters::new(8, 31, 5, mds, ark, 3, 1);
+
+        let input = Fr::from(7u64);
+        let input_var = vec![FpVar::new_witness(ns!(cs, "input"), || Ok(input)).unwrap()];
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&vec![input]);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        constraint_sponge.absorb(&input_var).unwrap();
+
+        let squeezed = constraint_sponge.squeeze_field_elements(1).unwrap();
+        assert_eq!(
+            squeezed.value().unwrap(),
+            native_sponge.squeeze_native_field_elements(1)
+        );
+        assert_eq!(constraint_sponge.permutation_count(), 1);
+
+        // The partially filled rate block must be permuted before it is squeezed.
+        assert_ne!(squeezed.value().unwrap(), vec![input]);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn absorb_into_capacity_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let input = vec![FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::from(5u64))).unwrap()];
+
+        let squeeze = |tag: Option<Fr>| {
+            let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            if let Some(tag) = tag {
+                let tag = FpVar::new_witness(ns!(cs, "tag"), || Ok(tag)).unwrap();
+                sponge.absorb_into_capacity(&[tag]);
+            }
+            sponge.absorb(&input).unwrap();
+            sponge.squeeze_field_elements(1).unwrap().value().unwrap()
+        };
+        let untagged = squeeze(None);
+        let tagged = squeeze(Some(Fr::from(1u64)));
+        assert_ne!(tagged, squeeze(Some(Fr::from(2u64))));
+        assert_ne!(tagged, untagged);
+        // A zero tag leaves the state untouched.
+        assert_eq!(squeeze(Some(Fr::zero())), untagged);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "the capacity can only be tagged before the sponge is used")]
+    fn absorb_into_capacity_after_absorb_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs, &sponge_params);
+        sponge.absorb(&FpVar::Constant(Fr::one())).unwrap();
+        sponge.absorb_into_capacity(&[FpVar::Constant(Fr::one())]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid Poseidon parameters: row 1 of the MDS matrix has 2 entries, but the state has 3 elements"
+    )]
+    fn ragged_mds_test() {
+        let mut sponge_params = poseidon_parameters_for_test::<Fr>();
+        sponge_params.mds[1].pop();
+        PoseidonSpongeVar::<Fr>::new(ConstraintSystem::new_ref(), &sponge_params);
+    }
+
+    #[test]
+    fn squeeze_bits_be_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let input = Fr::from(3u64);
+        let input_var = FpVar::new_witness(ns!(cs, "input"), || Ok(input)).unwrap();
+        let usable_bits = <Fr as PrimeField>::Params::CAPACITY as usize;
+
+        for num_bits in [0, 10, usable_bits, usable_bits + 10] {
+            let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+            native_sponge.absorb(&input);
+            let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            constraint_sponge.absorb(&input_var).unwrap();
+
+            let le = native_sponge.clone().squeeze_bits(num_bits);
+            let expected: Vec<bool> = le
+                .chunks(usable_bits)
+                .flat_map(|group| group.iter().rev().copied())
+                .collect();
+            assert_eq!(native_sponge.squeeze_bits_be(num_bits), expected);
+            assert_eq!(
+                constraint_sponge
+                    .squeeze_bits_be(num_bits)
+                    .unwrap()
+                    .value()
+                    .unwrap(),
+                expected
+            );
+        }
+
+        // A full group is the big-endian encoding of the low bits of the first element.
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&input);
+        let element = native_sponge.clone().squeeze_native_field_elements(1)[0];
+        let mut expected = element.into_repr().to_bits_be();
+        expected.drain(..expected.len() - usable_bits);
+        assert_eq!(native_sponge.squeeze_bits_be(usable_bits), expected);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn first_divergence_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let inputs: Vec<_> = (0..6u64)
+            .map(|i| FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::from(i))).unwrap())
+            .collect();
+
+        // With a rate of 2, absorbing 6 elements and squeezing one permutes 3 times.
+        let run = |inputs: &[FpVar<Fr>]| {
+            let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            sponge.absorb(&inputs.to_vec()).unwrap();
+            sponge.squeeze_field_elements(1).unwrap();
+            sponge
+        };
+        let sponge = run(&inputs);
+        assert_eq!(sponge.fingerprint().len(), 3);
+        assert_eq!(sponge.first_divergence(&run(&inputs)), None);
+
+        // Changing the fifth input only changes the block absorbed by the third permutation.
+        let mut diverging = inputs.clone();
+        diverging[4] = FpVar::Constant(Fr::from(100u64));
+        assert_eq!(sponge.first_divergence(&run(&diverging)), Some(2));
+
+        // A sponge that stopped early agrees with the other one as far as it went.
+        let mut longer = sponge.clone();
+        longer.squeeze_field_elements(3).unwrap();
+        assert_eq!(sponge.first_divergence(&longer), Some(3));
+        assert_eq!(longer.first_divergence(&sponge), Some(3));
+    }
+
+    #[test]
+    fn minimal_permutations_squeeze_test() {
+        let cs = ConstraintSystem::new_ref();
+        let (ark, mds) = find_poseidon_ark_and_mds::<Fr>(255, 3, 8, 31, 0);
+        let sponge_params = PoseidonParameters::new(8, 31, 5, mds, ark, 3, 1);
+        let input = FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::from(1u64))).unwrap();
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&Fr::from(1u64));
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        constraint_sponge.absorb(&input).unwrap();
+
+        // Switching to squeezing fills a first block of 3 elements, and each of the remaining
+        // 61 elements takes a permutation per 3 elements: `ceil(64 / 3) = 22` in total.
+        let squeezed = constraint_sponge.squeeze_field_elements(64).unwrap();
+        assert_eq!(constraint_sponge.permutation_count(), 22);
+        let expected = native_sponge.squeeze_native_field_elements(64);
+        assert_eq!(squeezed.value().unwrap(), expected);
+
+        // Squeezing in pieces that start in the middle of a block gives the same elements.
+        let mut pieces = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        pieces.absorb(&input).unwrap();
+        let mut squeezed = pieces.squeeze_field_elements(2).unwrap();
+        squeezed.append(&mut pieces.squeeze_field_elements(3).unwrap());
+        squeezed.append(&mut pieces.squeeze_field_elements(59).unwrap());
+        assert_eq!(pieces.permutation_count(), 22);
+        assert_eq!(squeezed.value().unwrap(), expected);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn absorb_into_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let inputs: Vec<_> = (0..5u64)
+            .map(|i| FpVar::new_witness(ns!(cs, "input"), || Ok(Fr::from(i))).unwrap())
+            .collect();
+        let bytes = UInt8::new_witness_vec(ns!(cs, "bytes"), &[1, 2, 3]).unwrap();
+
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        let mut expected = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        let mut scratch = Vec::with_capacity(4);
+        for input in &inputs {
+            sponge.absorb_into(input, &mut scratch).unwrap();
+            expected.absorb(input).unwrap();
+        }
+        sponge.absorb_into(&bytes, &mut scratch).unwrap();
+        expected.absorb(&bytes).unwrap();
+        // Single elements never outgrow the buffer, so it was not reallocated for them.
+        assert_eq!(scratch.capacity(), 4);
+
+        // Vectors, slices and arrays of elements are written to the buffer as they are.
+        let array = [inputs[0].clone(), inputs[1].clone()];
+        sponge.absorb_into(&inputs, &mut scratch).unwrap();
+        assert_eq!(scratch.value().unwrap(), inputs.value().unwrap());
+        sponge.absorb_into(&array, &mut scratch).unwrap();
+        assert_eq!(scratch.value().unwrap(), array.value().unwrap());
+        expected.absorb(&inputs).unwrap();
+        expected.absorb(&array).unwrap();
+        let nested = vec![inputs.clone(), vec![inputs[2].clone()]];
+        sponge.absorb_into(&nested, &mut scratch).unwrap();
+        assert_eq!(
+            scratch.value().unwrap(),
+            nested.to_sponge_field_elements().unwrap().value().unwrap()
+        );
+        expected.absorb(&nested).unwrap();
+
+        assert_eq!(
+            sponge.squeeze_field_elements(1).unwrap().value().unwrap(),
+            expected.squeeze_field_elements(1).unwrap().value().unwrap()
+        );
+    }
+
+    #[test]
+    fn state_test() {
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        let input = FpVar::new_witness(cs.clone(), || Ok(Fr::from(5u64))).unwrap();
+        sponge.absorb(&input).unwrap();
+
+        // The capacity comes first, and the input is added to the first rate element.
+        let state = sponge.state();
+        assert_eq!(state.len(), 3);
+        assert_eq!(
+            state.value().unwrap(),
+            vec![Fr::zero(), Fr::from(5u64), Fr::zero()]
+        );
+        state[1].enforce_equal(&input).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn mac_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+
+        let key = Fr::rand(&mut rng);
+        let message: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let mac = PoseidonMac::new(&sponge_params, key);
+        let tag = mac.mac(&message);
+        assert!(mac.verify(&message, &tag));
+        assert_ne!(
+            PoseidonMac::new(&sponge_params, key + Fr::one()).mac(&message),
+            tag
+        );
+
+        let key_var = FpVar::new_witness(cs.clone(), || Ok(key)).unwrap();
+        let message_var =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(message.clone())).unwrap();
+        let tag_var = FpVar::new_input(cs.clone(), || Ok(tag)).unwrap();
+        let mac_var = PoseidonMacVar::new(cs.clone(), &sponge_params, key_var);
+        assert_eq!(mac_var.mac(&message_var).unwrap().value().unwrap(), tag);
+        mac_var
+            .verify(&message_var, &tag_var)
+            .unwrap()
+            .enforce_equal(&Boolean::TRUE)
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // Any other tag does not verify.
+        let wrong_tag = FpVar::new_input(cs.clone(), || Ok(tag + Fr::one())).unwrap();
+        assert!(!mac_var
+            .verify(&message_var, &wrong_tag)
+            .unwrap()
+            .value()
+            .unwrap());
+    }
+
+    #[test]
+    fn absorb_opening_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let value_var = FpVar::new_witness(cs.clone(), || Ok(Fr::rand(&mut rng))).unwrap();
+        let randomness_var = FpVar::new_witness(cs.clone(), || Ok(Fr::rand(&mut rng))).unwrap();
+
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        sponge.absorb_opening(&value_var, &randomness_var).unwrap();
+
+        // The value comes first: swapping it with the randomness changes the transcript.
+        let mut swapped = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        swapped.absorb_opening(&randomness_var, &value_var).unwrap();
+        assert_ne!(
+            sponge.squeeze_field_elements(1).unwrap().value().unwrap(),
+            swapped.squeeze_field_elements(1).unwrap().value().unwrap()
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn from_native_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let first: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let second = Fr::rand(&mut rng);
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&first);
+        native_sponge.squeeze_native_field_elements(1);
+
+        // Resume the transcript in the circuit and finish it there.
+        let mut sponge = PoseidonSpongeVar::from_native(cs.clone(), &native_sponge);
+        let second_var = FpVar::new_witness(cs.clone(), || Ok(second)).unwrap();
+        sponge.absorb(&second_var).unwrap();
+        let squeezed = sponge.squeeze_field_elements(2).unwrap().value().unwrap();
+
+        native_sponge.absorb(&second);
+        assert_eq!(squeezed, native_sponge.squeeze_native_field_elements(2));
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn squeeze_and_commit_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let inputs: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+
+        let commit = |inputs: &[Fr]| {
+            let inputs_var =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(inputs.to_vec())).unwrap();
+            let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            sponge.absorb(&inputs_var).unwrap();
+            let (squeezed, digest) = sponge.squeeze_and_commit(2).unwrap();
+            // The next squeeze is not the digest.
+            let next = sponge.squeeze_field_elements(1).unwrap().value().unwrap();
+            assert_ne!(next[0], digest.value().unwrap());
+            (squeezed.value().unwrap(), digest.value().unwrap())
+        };
+
+        let (squeezed, digest) = commit(&inputs);
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&inputs);
+        assert_eq!(squeezed, native_sponge.squeeze_native_field_elements(2));
+        assert_eq!(commit(&inputs).1, digest);
+
+        for i in 0..inputs.len() {
+            let mut changed = inputs.clone();
+            changed[i] += Fr::one();
+            assert_ne!(commit(&changed).1, digest);
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn peek_field_element_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        let input = FpVar::new_witness(cs.clone(), || Ok(Fr::rand(&mut rng))).unwrap();
+        sponge.absorb(&input).unwrap();
+
+        // Peek right after absorbing, in the middle of a block, and at the end of a block.
+        for _ in 0..3 {
+            let peeked = sponge.peek_field_element().unwrap().value().unwrap();
+            assert_eq!(
+                sponge.peek_field_element().unwrap().value().unwrap(),
+                peeked
+            );
+            assert_eq!(
+                sponge.squeeze_field_elements(1).unwrap().value().unwrap(),
+                vec![peeked]
+            );
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn squeeze_bytes_with_endianness_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        let input = FpVar::new_witness(cs.clone(), || Ok(Fr::rand(&mut rng))).unwrap();
+        sponge.absorb(&input).unwrap();
+
+        // 40 bytes span two squeezed elements.
+        let little = sponge
+            .clone()
+            .squeeze_bytes_with_endianness(40, Endianness::Little)
+            .unwrap()
+            .value()
+            .unwrap();
+        let mut big = sponge
+            .clone()
+            .squeeze_bytes_with_endianness(40, Endianness::Big)
+            .unwrap()
+            .value()
+            .unwrap();
+        assert_eq!(little, sponge.squeeze_bytes(40).unwrap().value().unwrap());
+        big.reverse();
+        assert_eq!(big, little);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn ratchet_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        let input = FpVar::new_witness(cs.clone(), || Ok(Fr::rand(&mut rng))).unwrap();
+        sponge.absorb(&input).unwrap();
+        sponge.squeeze_field_elements(1).unwrap();
+
+        let mut unratcheted = sponge.clone();
+        sponge.ratchet().unwrap();
+        assert_eq!(
+            sponge.state()[sponge_params.capacity..].value().unwrap(),
+            vec![Fr::zero(); sponge_params.rate]
+        );
+        assert!(!sponge.state()[0].value().unwrap().is_zero());
+        assert_ne!(
+            sponge.squeeze_field_elements(2).unwrap().value().unwrap(),
+            unratcheted
+                .squeeze_field_elements(2)
+                .unwrap()
+                .value()
+                .unwrap()
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn prf_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let key = Fr::rand(&mut rng);
+        let input: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+
+        let prf = PoseidonPrf::new(&sponge_params, key);
+        let output = prf.prf(&input, 5);
+        // Shorter outputs are prefixes of longer ones.
+        assert_eq!(prf.prf(&input, 2), output[..2]);
+        // The capacity tag sets outputs apart from MAC tags under the same key.
+        assert_ne!(output[0], PoseidonMac::new(&sponge_params, key).mac(&input));
+
+        let key_var = FpVar::new_witness(cs.clone(), || Ok(key)).unwrap();
+        let input_var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(input.clone())).unwrap();
+        let prf_var = PoseidonPrfVar::new(cs.clone(), &sponge_params, key_var);
+        assert_eq!(prf_var.prf(&input_var, 5).unwrap().value().unwrap(), output);
+        assert_eq!(
+            prf_var.prf(&input_var, 2).unwrap().value().unwrap(),
+            output[..2]
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn absorb_internal_full_block_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let block = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || {
+            Ok(vec![Fr::rand(&mut rng), Fr::rand(&mut rng)])
+        })
+        .unwrap();
+        let next = FpVar::new_witness(cs.clone(), || Ok(Fr::rand(&mut rng))).unwrap();
+
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        sponge.absorb(&block).unwrap();
+        let mut expected = sponge.clone();
+        expected.absorb(&next).unwrap();
+
+        sponge.absorb_internal(sponge_params.rate, &[next]).unwrap();
+        assert_eq!(
+            sponge.state.value().unwrap(),
+            expected.state.value().unwrap()
+        );
+        assert_eq!(sponge.permutation_count(), 1);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn interleaved_absorb_squeeze_fuzz_test() {
+        let mut rng = test_rng();
+        let (ark, mds) = find_poseidon_ark_and_mds::<Fr>(255, 3, 8, 31, 0);
+        let rate_3_params = PoseidonParameters::new(8, 31, 5, mds, ark, 3, 1);
+        for sponge_params in [poseidon_parameters_for_test(), rate_3_params] {
+            let cs = ConstraintSystem::new_ref();
+            let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+            let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+
+            for _ in 0..60 {
+                let num_elements = rng.gen_range(0..7);
+                if rng.gen() {
+                    let input: Vec<_> = (0..num_elements).map(|_| Fr::rand(&mut rng)).collect();
+                    let input_var =
+                        Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(input.clone())).unwrap();
+                    native_sponge.absorb(&input);
+                    constraint_sponge.absorb(&input_var).unwrap();
+                } else {
+                    assert_eq!(
+                        constraint_sponge
+                            .squeeze_field_elements(num_elements)
+                            .unwrap()
+                            .value()
+                            .unwrap(),
+                        native_sponge.squeeze_native_field_elements(num_elements)
+                    );
+                }
+                assert_eq!(
+                    constraint_sponge.state.value().unwrap(),
+                    native_sponge.state
+                );
+            }
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn absorb_bits_packed_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let bits: Vec<bool> = (0..256).map(|_| rng.gen()).collect();
+        let bits_var = Vec::<Boolean<Fr>>::new_witness(cs.clone(), || Ok(bits.clone())).unwrap();
+
+        let packed = || {
+            let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+            sponge.absorb_bits_packed(&bits_var).unwrap();
+            sponge.squeeze_field_elements(1).unwrap().value().unwrap()
+        };
+        let squeezed = packed();
+        assert_eq!(packed(), squeezed);
+
+        // The 256 bits fit in two elements of 254 bits, after the length.
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        let pack = |chunk: &[bool]| {
+            Fr::from_repr(<Fr as PrimeField>::BigInt::from_bits_le(chunk)).unwrap()
+        };
+        native_sponge.absorb(&vec![
+            Fr::from(256u64),
+            pack(&bits[..254]),
+            pack(&bits[254..]),
+        ]);
+        assert_eq!(squeezed, native_sponge.squeeze_native_field_elements(1));
+
+        let mut unpacked = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        unpacked.absorb(&bits_var).unwrap();
+        assert_ne!(
+            squeezed,
+            unpacked.squeeze_field_elements(1).unwrap().value().unwrap()
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn squeeze_permutation_test() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+        let input = Fr::rand(&mut rng);
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&input);
+        let mut constraint_sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+        constraint_sponge
+            .absorb(&FpVar::new_witness(cs.clone(), || Ok(input)).unwrap())
+            .unwrap();
+
+        for n in [0, 1, 2, 10] {
+            let permutation = native_sponge.clone().squeeze_permutation(n);
+            assert_eq!(native_sponge.squeeze_permutation(n), permutation);
+            let mut sorted = permutation.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, (0..n).collect::<Vec<_>>());
+
+            let permutation_var = constraint_sponge.squeeze_permutation(n).unwrap();
+            let expected: Vec<_> = permutation.iter().map(|i| Fr::from(*i as u64)).collect();
+            assert_eq!(permutation_var.value().unwrap(), expected);
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn simulate_test() {
+        let mut rng = test_rng();
+        let sponge_params = poseidon_parameters_for_test();
+        let inputs: Vec<_> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+
+        let mut native_sponge = PoseidonSponge::<Fr>::new(&sponge_params);
+        native_sponge.absorb(&inputs);
+        assert_eq!(
+            simulate(&sponge_params, &inputs, 3),
+            native_sponge.squeeze_native_field_elements(3)
+        );
+    }
 }
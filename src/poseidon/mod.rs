@@ -1,9 +1,11 @@
 use crate::{
     batch_field_cast, squeeze_field_elements_with_sizes_default_impl, Absorb, CryptographicSponge,
-    DuplexSpongeMode, FieldBasedCryptographicSponge, FieldElementSize, SpongeExt,
+    DuplexSpongeMode, FieldBasedCryptographicSponge, FieldElementSize, SpongeExt, StateVec,
 };
 use ark_ff::{BigInteger, FpParameters, PrimeField};
 use ark_std::any::TypeId;
+use ark_std::fmt;
+use ark_std::io::{self, Read, Write};
 use ark_std::vec;
 use ark_std::vec::Vec;
 
@@ -11,7 +13,7 @@ use ark_std::vec::Vec;
 #[cfg(feature = "r1cs")]
 pub mod constraints;
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
 
 /// default parameters traits for Poseidon
 pub mod traits;
@@ -39,6 +41,26 @@ pub struct PoseidonParameters<F: PrimeField> {
     pub rate: usize,
     /// The capacity (in terms of number of field elements).
     pub capacity: usize,
+    /// The number of elements squeezed per permutation, from the start of the rate. It must be
+    /// between 1 and `rate`, and `None` squeezes the whole rate. A smaller squeeze rate
+    /// permutes more often and leaves more of each state unrevealed.
+    pub squeeze_rate: Option<usize>,
+    /// The initial values of the capacity elements, for domain separation. It must have
+    /// `capacity` elements, and `None` starts them at zero. Sponges with different IVs do not
+    /// collide, even if they share every other parameter.
+    pub initial_state_iv: Option<Vec<F>>,
+    /// How absorbed elements enter the rate, and `None` adds them as `AbsorbMode::Add` does.
+    pub absorb_mode: Option<AbsorbMode>,
+}
+
+/// How a Poseidon sponge absorbs an element into its rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbsorbMode {
+    /// The element is added to the rate position, as in the duplex construction.
+    Add,
+    /// The element replaces the rate position. This is only sound for the constructions
+    /// analysed with it, such as some keyed sponges, and is not a drop-in alternative to `Add`.
+    Overwrite,
 }
 
 #[derive(Clone)]
@@ -54,78 +76,107 @@ pub struct PoseidonSponge<F: PrimeField> {
 
     // Sponge State
     /// Current sponge's state (current elements in the permutation block)
-    pub state: Vec<F>,
+    ///
+    /// It must always hold `parameters.rate + parameters.capacity` elements. This is checked by
+    /// debug assertions when the state is permuted, absorbed into or squeezed from.
+    pub state: StateVec<F>,
     /// Current mode (whether its absorbing or squeezing)
     pub mode: DuplexSpongeMode,
 }
 
 impl<F: PrimeField> PoseidonSponge<F> {
-    fn apply_s_box(&self, state: &mut [F], is_full_round: bool) {
+    fn apply_s_box(parameters: &PoseidonParameters<F>, state: &mut [F], is_full_round: bool) {
         // Full rounds apply the S Box (x^alpha) to every element of state
         if is_full_round {
             for elem in state {
-                *elem = elem.pow(&[self.parameters.alpha]);
+                *elem = elem.pow(&[parameters.alpha]);
             }
         }
         // Partial rounds apply the S Box (x^alpha) to just the first element of state
         else {
-            state[0] = state[0].pow(&[self.parameters.alpha]);
+            state[0] = state[0].pow(&[parameters.alpha]);
         }
     }
 
-    fn apply_ark(&self, state: &mut [F], round_number: usize) {
+    fn apply_ark(parameters: &PoseidonParameters<F>, state: &mut [F], round_number: usize) {
         for (i, state_elem) in state.iter_mut().enumerate() {
-            state_elem.add_assign(&self.parameters.ark[round_number][i]);
+            state_elem.add_assign(&parameters.ark[round_number][i]);
         }
     }
 
-    fn apply_mds(&self, state: &mut [F]) {
-        let mut new_state = Vec::new();
+    // `scratch` is only used as a buffer for the new state, so that a permutation allocates it
+    // once rather than once per round.
+    fn apply_mds(parameters: &PoseidonParameters<F>, state: &mut [F], scratch: &mut Vec<F>) {
+        scratch.clear();
         for i in 0..state.len() {
             let mut cur = F::zero();
             for (j, state_elem) in state.iter().enumerate() {
-                let term = state_elem.mul(&self.parameters.mds[i][j]);
+                let term = state_elem.mul(&parameters.mds[i][j]);
                 cur.add_assign(&term);
             }
-            new_state.push(cur);
+            scratch.push(cur);
         }
-        state.clone_from_slice(&new_state[..state.len()])
+        state.copy_from_slice(scratch)
     }
 
-    fn permute(&mut self) {
-        let full_rounds_over_2 = self.parameters.full_rounds / 2;
-        let mut state = self.state.clone();
+    // Applies the permutation described by `parameters` to `state`, using `scratch` as the
+    // buffer of `apply_mds`.
+    fn permute_state(parameters: &PoseidonParameters<F>, state: &mut [F], scratch: &mut Vec<F>) {
+        let full_rounds_over_2 = parameters.full_rounds / 2;
         for i in 0..full_rounds_over_2 {
-            self.apply_ark(&mut state, i);
-            self.apply_s_box(&mut state, true);
-            self.apply_mds(&mut state);
+            Self::apply_ark(parameters, state, i);
+            Self::apply_s_box(parameters, state, true);
+            Self::apply_mds(parameters, state, scratch);
         }
 
-        for i in full_rounds_over_2..(full_rounds_over_2 + self.parameters.partial_rounds) {
-            self.apply_ark(&mut state, i);
-            self.apply_s_box(&mut state, false);
-            self.apply_mds(&mut state);
+        for i in full_rounds_over_2..(full_rounds_over_2 + parameters.partial_rounds) {
+            Self::apply_ark(parameters, state, i);
+            Self::apply_s_box(parameters, state, false);
+            Self::apply_mds(parameters, state, scratch);
         }
 
-        for i in (full_rounds_over_2 + self.parameters.partial_rounds)
-            ..(self.parameters.partial_rounds + self.parameters.full_rounds)
+        for i in (full_rounds_over_2 + parameters.partial_rounds)
+            ..(parameters.partial_rounds + parameters.full_rounds)
         {
-            self.apply_ark(&mut state, i);
-            self.apply_s_box(&mut state, true);
-            self.apply_mds(&mut state);
+            Self::apply_ark(parameters, state, i);
+            Self::apply_s_box(parameters, state, true);
+            Self::apply_mds(parameters, state, scratch);
         }
-        self.state = state;
+    }
+
+    fn permute(&mut self) {
+        debug_assert_eq!(
+            self.state.len(),
+            self.parameters.rate + self.parameters.capacity,
+            "the state must have `rate + capacity` elements"
+        );
+        #[cfg(feature = "poseidon-trace")]
+        tracing::trace!(state = %HexState(&self.state), "permutation input");
+        let mut scratch = Vec::with_capacity(self.state.len());
+        Self::permute_state(&self.parameters, &mut self.state, &mut scratch);
+        #[cfg(feature = "poseidon-trace")]
+        tracing::trace!(state = %HexState(&self.state), "permutation output");
     }
 
     // Absorbs everything in elements, this does not end in an absorbtion.
     fn absorb_internal(&mut self, mut rate_start_index: usize, elements: &[F]) {
+        debug_assert_eq!(
+            self.state.len(),
+            self.parameters.rate + self.parameters.capacity,
+            "the state must have `rate + capacity` elements"
+        );
+        // A full block is permuted before anything more is absorbed into the rate.
+        if rate_start_index == self.parameters.rate && !elements.is_empty() {
+            self.permute();
+            rate_start_index = 0;
+        }
         let mut remaining_elements = elements;
 
         loop {
             // if we can finish in this call
             if rate_start_index + remaining_elements.len() <= self.parameters.rate {
                 for (i, element) in remaining_elements.iter().enumerate() {
-                    self.state[self.parameters.capacity + i + rate_start_index] += element;
+                    self.absorb_at(i + rate_start_index, element);
                 }
                 self.mode = DuplexSpongeMode::Absorbing {
                     next_absorb_index: rate_start_index + remaining_elements.len(),
@@ -140,7 +191,7 @@ impl<F: PrimeField> PoseidonSponge<F> {
                 .enumerate()
                 .take(num_elements_absorbed)
             {
-                self.state[self.parameters.capacity + i + rate_start_index] += element;
+                self.absorb_at(i + rate_start_index, element);
             }
             self.permute();
             // the input elements got truncated by num elements absorbed
@@ -149,12 +200,27 @@ impl<F: PrimeField> PoseidonSponge<F> {
         }
     }
 
+    // Absorbs one element at `rate_index` of the rate, following the absorb mode.
+    fn absorb_at(&mut self, rate_index: usize, element: &F) {
+        let position = &mut self.state[self.parameters.capacity + rate_index];
+        match self.parameters.effective_absorb_mode() {
+            AbsorbMode::Add => *position += element,
+            AbsorbMode::Overwrite => *position = *element,
+        }
+    }
+
     // Squeeze |output| many elements. This does not end in a squeeze
     fn squeeze_internal(&mut self, mut rate_start_index: usize, output: &mut [F]) {
+        debug_assert_eq!(
+            self.state.len(),
+            self.parameters.rate + self.parameters.capacity,
+            "the state must have `rate + capacity` elements"
+        );
+        let squeeze_rate = self.parameters.effective_squeeze_rate();
         let mut output_remaining = output;
         loop {
             // if we can finish in this call
-            if rate_start_index + output_remaining.len() <= self.parameters.rate {
+            if rate_start_index + output_remaining.len() <= squeeze_rate {
                 output_remaining.clone_from_slice(
                     &self.state[self.parameters.capacity + rate_start_index
                         ..(self.parameters.capacity + output_remaining.len() + rate_start_index)],
@@ -164,25 +230,442 @@ impl<F: PrimeField> PoseidonSponge<F> {
                 };
                 return;
             }
-            // otherwise squeeze (rate - rate_start_index) elements
-            let num_elements_squeezed = self.parameters.rate - rate_start_index;
+            // otherwise squeeze (squeeze_rate - rate_start_index) elements
+            let num_elements_squeezed = squeeze_rate - rate_start_index;
             output_remaining[..num_elements_squeezed].clone_from_slice(
                 &self.state[self.parameters.capacity + rate_start_index
                     ..(self.parameters.capacity + num_elements_squeezed + rate_start_index)],
             );
 
-            // Unless we are done with squeezing in this call, permute.
-            if output_remaining.len() != self.parameters.rate {
-                self.permute();
-            }
+            // More elements are left to squeeze, and they come from the next block.
+            self.permute();
             // Repeat with updated output slices
             output_remaining = &mut output_remaining[num_elements_squeezed..];
             rate_start_index = 0;
         }
     }
+
+    // Absorbs native field elements, switching to absorbing mode if needed.
+    fn absorb_elements(&mut self, elems: &[F]) {
+        if elems.is_empty() {
+            return;
+        }
+
+        match self.mode {
+            DuplexSpongeMode::Absorbing { next_absorb_index } => {
+                let mut absorb_index = next_absorb_index;
+                if absorb_index == self.parameters.rate {
+                    self.permute();
+                    absorb_index = 0;
+                }
+                self.absorb_internal(absorb_index, elems);
+            }
+            DuplexSpongeMode::Squeezing {
+                next_squeeze_index: _,
+            } => {
+                self.permute();
+                self.absorb_internal(0, elems);
+            }
+        };
+    }
+
+    /// Applies the `10*1` multi-rate padding to the absorbed input, as in pure sponge hashing.
+    ///
+    /// The padding is a one, as many zeros as needed, and a final one, such that it ends on the
+    /// last element of a rate block. It takes at least two elements, so when fewer than two
+    /// positions are left in the current block, it spans the next block too. Call this once
+    /// after the last `absorb` and before the first squeeze; the squeeze then applies the final
+    /// permutation. `PoseidonSpongeVar::finalize_absorb` applies the same padding.
+    pub fn finalize_absorb(&mut self) {
+        let padding = multirate_padding::<F>(self.parameters.rate, &self.mode);
+        self.absorb_elements(&padding);
+    }
+
+    /// Squeezes `num_bits` bits like `squeeze_bits`, but with the bits of each squeezed element
+    /// in big-endian order.
+    ///
+    /// `squeeze_bits` outputs the `F::Params::CAPACITY` low bits of each element, least
+    /// significant first. This reverses each of these groups in place, so elements still come
+    /// in squeezing order, each from its most significant bit down. When `num_bits` is not a
+    /// multiple of the capacity, the last group holds the low bits of the last element, most
+    /// significant first. `PoseidonSpongeVar::squeeze_bits_be` outputs the same bits.
+    pub fn squeeze_bits_be(&mut self, num_bits: usize) -> Vec<bool> {
+        let usable_bits = F::Params::CAPACITY as usize;
+        self.squeeze_bits(num_bits)
+            .chunks(usable_bits)
+            .flat_map(|group| group.iter().rev().copied())
+            .collect()
+    }
+
+    /// Absorbs everything `reader` yields until its end, holding a single chunk in memory.
+    ///
+    /// The input is absorbed as one element per chunk of `F::Params::CAPACITY / 8` bytes, read
+    /// as a little-endian integer, followed by the total number of bytes. The length comes
+    /// last because it is only known at the end of the stream, so this is not the encoding of
+    /// absorbing the same bytes as a `&[u8]`, which prefixes it. The result does not depend on
+    /// how `reader` splits its output between reads, and reads failing with
+    /// `io::ErrorKind::Interrupted` are retried. Without the `std` feature, `Read` is the one of
+    /// `ark_std::io`.
+    pub fn absorb_reader(&mut self, mut reader: impl Read) -> io::Result<()> {
+        let chunk_len = (F::Params::CAPACITY / 8) as usize;
+        let mut chunk = vec![0u8; chunk_len];
+        let mut total_len = 0u64;
+        loop {
+            let mut filled = 0;
+            while filled < chunk_len {
+                match reader.read(&mut chunk[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            if filled == 0 {
+                break;
+            }
+            total_len += filled as u64;
+            self.absorb_elements(&[F::from_le_bytes_mod_order(&chunk[..filled])]);
+            if filled < chunk_len {
+                break;
+            }
+        }
+        self.absorb_elements(&[F::from(total_len)]);
+        Ok(())
+    }
+
+    /// Squeezes `num_bytes` bytes, as `squeeze_bytes` does, and writes them to `out`.
+    pub fn squeeze_writer(&mut self, mut out: impl Write, num_bytes: usize) -> io::Result<()> {
+        out.write_all(&self.squeeze_bytes(num_bytes))
+    }
+}
+
+/// The `10*1` padding that makes the next absorption end on the last element of a rate block.
+pub(crate) fn multirate_padding<F: PrimeField>(rate: usize, mode: &DuplexSpongeMode) -> Vec<F> {
+    // Absorbing after squeezing starts a new block.
+    let absorb_index = match mode {
+        DuplexSpongeMode::Absorbing { next_absorb_index } => *next_absorb_index,
+        DuplexSpongeMode::Squeezing { .. } => 0,
+    };
+
+    let mut padding_len = rate - absorb_index;
+    while padding_len < 2 {
+        padding_len += rate;
+    }
+
+    let mut padding = vec![F::zero(); padding_len];
+    padding[0] = F::one();
+    padding[padding_len - 1] = F::one();
+    padding
+}
+
+/// Displays a state as a list of big-endian hexadecimal field elements, for the permutation
+/// traces of the `poseidon-trace` feature.
+#[cfg(feature = "poseidon-trace")]
+pub(crate) struct HexState<'a, F>(pub(crate) &'a [F]);
+
+#[cfg(feature = "poseidon-trace")]
+impl<F: PrimeField> fmt::Display for HexState<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, elem) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "0x")?;
+            for byte in elem.into_repr().to_bytes_be() {
+                write!(f, "{:02x}", byte)?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+/// An error raised when Poseidon parameters are inconsistent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParameterError {
+    /// The rate is zero, so the sponge cannot absorb or squeeze anything.
+    ZeroRate,
+    /// The capacity is zero, so the sponge offers no security.
+    ZeroCapacity,
+    /// The MDS matrix does not have `rate + capacity` rows.
+    MdsRowCount {
+        /// The expected number of rows.
+        expected: usize,
+        /// The actual number of rows.
+        found: usize,
+    },
+    /// A row of the MDS matrix does not have `rate + capacity` entries.
+    MdsRowLength {
+        /// The index of the offending row.
+        row: usize,
+        /// The expected number of entries.
+        expected: usize,
+        /// The actual number of entries.
+        found: usize,
+    },
+    /// The additive round keys do not have one row per round.
+    ArkRowCount {
+        /// The expected number of rows.
+        expected: usize,
+        /// The actual number of rows.
+        found: usize,
+    },
+    /// A row of the additive round keys does not have `rate + capacity` entries.
+    ArkRowLength {
+        /// The index of the offending row.
+        row: usize,
+        /// The expected number of entries.
+        expected: usize,
+        /// The actual number of entries.
+        found: usize,
+    },
+    /// `alpha` is not coprime to `p - 1`, so the S-box `x^alpha` is not a permutation.
+    AlphaNotCoprime {
+        /// The offending exponent.
+        alpha: u64,
+    },
+    /// The MDS matrix is singular, so the linear layer of the permutation loses information.
+    SingularMds,
+    /// The squeeze rate is zero or greater than the rate.
+    SqueezeRate {
+        /// The offending squeeze rate.
+        squeeze_rate: usize,
+        /// The rate.
+        rate: usize,
+    },
+    /// The initial state IV does not have one element per capacity element.
+    IvLength {
+        /// The expected number of elements.
+        expected: usize,
+        /// The actual number of elements.
+        found: usize,
+    },
+    /// `alpha` is less than 3. For `alpha = 1`, which is coprime to `p - 1`, the S-box `x^alpha`
+    /// is linear and the permutation offers no security.
+    AlphaTooSmall {
+        /// The offending exponent.
+        alpha: u64,
+    },
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterError::ZeroRate => write!(f, "the rate must be at least 1"),
+            ParameterError::ZeroCapacity => write!(f, "the capacity must be at least 1"),
+            ParameterError::MdsRowCount { expected, found } => write!(
+                f,
+                "the MDS matrix has {} rows, but the state has {} elements",
+                found, expected
+            ),
+            ParameterError::MdsRowLength {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} of the MDS matrix has {} entries, but the state has {} elements",
+                row, found, expected
+            ),
+            ParameterError::ArkRowCount { expected, found } => write!(
+                f,
+                "the round keys have {} rows, but there are {} rounds",
+                found, expected
+            ),
+            ParameterError::ArkRowLength {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} of the round keys has {} entries, but the state has {} elements",
+                row, found, expected
+            ),
+            ParameterError::AlphaNotCoprime { alpha } => write!(
+                f,
+                "alpha = {} is not coprime to p - 1, so x^alpha is not a permutation",
+                alpha
+            ),
+            ParameterError::SingularMds => write!(f, "the MDS matrix is not invertible"),
+            ParameterError::SqueezeRate { squeeze_rate, rate } => write!(
+                f,
+                "the squeeze rate {} is not between 1 and the rate {}",
+                squeeze_rate, rate
+            ),
+            ParameterError::IvLength { expected, found } => write!(
+                f,
+                "the initial state IV has {} elements, but the capacity is {}",
+                found, expected
+            ),
+            ParameterError::AlphaTooSmall { alpha } => write!(
+                f,
+                "alpha = {} is less than 3, so x^alpha is not a secure S-box",
+                alpha
+            ),
+        }
+    }
+}
+
+impl ark_std::error::Error for ParameterError {}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+// Checks whether `gcd(alpha, p - 1) = 1`, where `p` is the characteristic of `F`.
+fn is_coprime_to_p_minus_one<F: PrimeField>(alpha: u64) -> bool {
+    if alpha == 0 {
+        return false;
+    }
+
+    // `p` is an odd prime, so `p - 1` only differs from `p` in the lowest limb.
+    let mut p_minus_one_mod_alpha = 0u64;
+    for (i, limb) in F::characteristic().iter().enumerate().rev() {
+        let limb = if i == 0 { limb - 1 } else { *limb };
+        let acc = ((p_minus_one_mod_alpha as u128) << 64) | limb as u128;
+        p_minus_one_mod_alpha = (acc % alpha as u128) as u64;
+    }
+
+    gcd(alpha, p_minus_one_mod_alpha) == 1
+}
+
+// Checks whether the square `matrix` is invertible, by Gaussian elimination.
+fn is_invertible<F: PrimeField>(matrix: &[Vec<F>]) -> bool {
+    let mut rows = matrix.to_vec();
+    for col in 0..rows.len() {
+        let pivot = match (col..rows.len()).find(|&row| !rows[row][col].is_zero()) {
+            Some(pivot) => pivot,
+            None => return false,
+        };
+        rows.swap(col, pivot);
+
+        let inverse = rows[col][col].inverse().unwrap();
+        let pivot_row = rows[col].clone();
+        for row in rows.iter_mut().skip(col + 1) {
+            let factor = row[col] * inverse;
+            for (elem, pivot_elem) in row.iter_mut().zip(&pivot_row).skip(col) {
+                *elem -= factor * pivot_elem;
+            }
+        }
+    }
+    true
 }
 
 impl<F: PrimeField> PoseidonParameters<F> {
+    /// Initialize the parameter for Poseidon Sponge, checking that the parameters are
+    /// consistent: the rate and the capacity are nonzero, the MDS matrix is square with one row
+    /// per state element and invertible, there is one row of round keys per round with one key
+    /// per state element, and `alpha` is coprime to `p - 1` and at least 3.
+    pub fn new_checked(
+        full_rounds: usize,
+        partial_rounds: usize,
+        alpha: u64,
+        mds: Vec<Vec<F>>,
+        ark: Vec<Vec<F>>,
+        rate: usize,
+        capacity: usize,
+    ) -> Result<Self, ParameterError> {
+        let parameters = Self {
+            full_rounds,
+            partial_rounds,
+            alpha,
+            mds,
+            ark,
+            rate,
+            capacity,
+            squeeze_rate: None,
+            initial_state_iv: None,
+            absorb_mode: None,
+        };
+        parameters.check_dimensions()?;
+
+        if capacity == 0 {
+            return Err(ParameterError::ZeroCapacity);
+        }
+        if !is_invertible(&parameters.mds) {
+            return Err(ParameterError::SingularMds);
+        }
+        if !is_coprime_to_p_minus_one::<F>(alpha) {
+            return Err(ParameterError::AlphaNotCoprime { alpha });
+        }
+        if alpha < 3 {
+            return Err(ParameterError::AlphaTooSmall { alpha });
+        }
+
+        Ok(parameters)
+    }
+
+    /// Checks that the rate is nonzero, that the MDS matrix is square with one row per state
+    /// element, that there is one row of round keys per round with one key per state element,
+    /// that the squeeze rate is between 1 and the rate, and that the initial state IV has one
+    /// element per capacity element.
+    ///
+    /// The sponges panic with the message of this error when they are created from parameters
+    /// that fail this check, rather than looping forever on their first absorb or indexing out
+    /// of bounds in the middle of a permutation.
+    pub fn check_dimensions(&self) -> Result<(), ParameterError> {
+        if self.rate == 0 {
+            return Err(ParameterError::ZeroRate);
+        }
+        let state_len = self.rate + self.capacity;
+
+        if self.mds.len() != state_len {
+            return Err(ParameterError::MdsRowCount {
+                expected: state_len,
+                found: self.mds.len(),
+            });
+        }
+        for (row, item) in self.mds.iter().enumerate() {
+            if item.len() != state_len {
+                return Err(ParameterError::MdsRowLength {
+                    row,
+                    expected: state_len,
+                    found: item.len(),
+                });
+            }
+        }
+
+        if self.ark.len() != self.full_rounds + self.partial_rounds {
+            return Err(ParameterError::ArkRowCount {
+                expected: self.full_rounds + self.partial_rounds,
+                found: self.ark.len(),
+            });
+        }
+        for (row, item) in self.ark.iter().enumerate() {
+            if item.len() != state_len {
+                return Err(ParameterError::ArkRowLength {
+                    row,
+                    expected: state_len,
+                    found: item.len(),
+                });
+            }
+        }
+
+        if let Some(squeeze_rate) = self.squeeze_rate {
+            if squeeze_rate == 0 || squeeze_rate > self.rate {
+                return Err(ParameterError::SqueezeRate {
+                    squeeze_rate,
+                    rate: self.rate,
+                });
+            }
+        }
+        if let Some(iv) = &self.initial_state_iv {
+            if iv.len() != self.capacity {
+                return Err(ParameterError::IvLength {
+                    expected: self.capacity,
+                    found: iv.len(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Initialize the parameter for Poseidon Sponge.
     pub fn new(
         full_rounds: usize,
@@ -209,7 +692,32 @@ impl<F: PrimeField> PoseidonParameters<F> {
             ark,
             rate,
             capacity,
+            squeeze_rate: None,
+            initial_state_iv: None,
+            absorb_mode: None,
+        }
+    }
+
+    /// The number of elements squeezed per permutation: `squeeze_rate`, or `rate` if it is
+    /// `None`.
+    pub fn effective_squeeze_rate(&self) -> usize {
+        self.squeeze_rate.unwrap_or(self.rate)
+    }
+
+    /// The way elements are absorbed into the rate: `absorb_mode`, or `AbsorbMode::Add` if it
+    /// is `None`.
+    pub fn effective_absorb_mode(&self) -> AbsorbMode {
+        self.absorb_mode.unwrap_or(AbsorbMode::Add)
+    }
+
+    /// The state of a fresh sponge: the capacity elements hold `initial_state_iv`, or zeros if
+    /// it is `None`, and the rate elements are zero.
+    pub fn initial_state(&self) -> Vec<F> {
+        let mut state = vec![F::zero(); self.rate + self.capacity];
+        if let Some(iv) = &self.initial_state_iv {
+            state[..self.capacity].clone_from_slice(iv);
         }
+        state
     }
 }
 
@@ -217,7 +725,10 @@ impl<F: PrimeField> CryptographicSponge for PoseidonSponge<F> {
     type Parameters = PoseidonParameters<F>;
 
     fn new(parameters: &Self::Parameters) -> Self {
-        let state = vec![F::zero(); parameters.rate + parameters.capacity];
+        if let Err(error) = parameters.check_dimensions() {
+            panic!("invalid Poseidon parameters: {}", error);
+        }
+        let state = parameters.initial_state().into();
         let mode = DuplexSpongeMode::Absorbing {
             next_absorb_index: 0,
         };
@@ -231,26 +742,7 @@ impl<F: PrimeField> CryptographicSponge for PoseidonSponge<F> {
 
     fn absorb(&mut self, input: &impl Absorb) {
         let elems = input.to_sponge_field_elements_as_vec::<F>();
-        if elems.is_empty() {
-            return;
-        }
-
-        match self.mode {
-            DuplexSpongeMode::Absorbing { next_absorb_index } => {
-                let mut absorb_index = next_absorb_index;
-                if absorb_index == self.parameters.rate {
-                    self.permute();
-                    absorb_index = 0;
-                }
-                self.absorb_internal(absorb_index, elems.as_slice());
-            }
-            DuplexSpongeMode::Squeezing {
-                next_squeeze_index: _,
-            } => {
-                self.permute();
-                self.absorb_internal(0, elems.as_slice());
-            }
-        };
+        self.absorb_elements(&elems);
     }
 
     fn squeeze_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
@@ -319,6 +811,11 @@ impl<F: PrimeField> CryptographicSponge for PoseidonSponge<F> {
 
 impl<F: PrimeField> FieldBasedCryptographicSponge<F> for PoseidonSponge<F> {
     fn squeeze_native_field_elements(&mut self, num_elements: usize) -> Vec<F> {
+        // An empty squeeze must not permute the state or switch the mode.
+        if num_elements == 0 {
+            return Vec::new();
+        }
+
         let mut squeezed_elems = vec![F::zero(); num_elements];
         match self.mode {
             DuplexSpongeMode::Absorbing {
@@ -329,7 +826,7 @@ impl<F: PrimeField> FieldBasedCryptographicSponge<F> for PoseidonSponge<F> {
             }
             DuplexSpongeMode::Squeezing { next_squeeze_index } => {
                 let mut squeeze_index = next_squeeze_index;
-                if squeeze_index == self.parameters.rate {
+                if squeeze_index == self.parameters.effective_squeeze_rate() {
                     self.permute();
                     squeeze_index = 0;
                 }
@@ -344,7 +841,7 @@ impl<F: PrimeField> FieldBasedCryptographicSponge<F> for PoseidonSponge<F> {
 #[derive(Clone)]
 /// Stores the state of a Poseidon Sponge. Does not store any parameter.
 pub struct PoseidonSpongeState<F: PrimeField> {
-    state: Vec<F>,
+    state: StateVec<F>,
     mode: DuplexSpongeMode,
 }
 
@@ -366,6 +863,236 @@ impl<CF: PrimeField> SpongeExt for PoseidonSponge<CF> {
     }
 }
 
+#[derive(Clone)]
+/// An incremental hasher of byte strings built on `PoseidonSponge`.
+///
+/// The input is absorbed as it arrives, with the encoding of `PoseidonSponge::absorb_reader`:
+/// one element per chunk of `F::Params::CAPACITY / 8` bytes, then the total number of bytes.
+/// Only the last, partial chunk is buffered. The digest is the first native field element
+/// squeezed afterwards, so it does not depend on how the input is split between calls, but it
+/// differs from the one of a sponge absorbing the same bytes as a single `&[u8]`, which
+/// prefixes the length.
+pub struct PoseidonHasher<F: PrimeField> {
+    sponge: PoseidonSponge<F>,
+    tail: Vec<u8>,
+    total_len: u64,
+}
+
+impl<F: PrimeField> PoseidonHasher<F> {
+    /// Creates a hasher with an empty input.
+    pub fn new(parameters: &PoseidonParameters<F>) -> Self {
+        Self {
+            sponge: PoseidonSponge::new(parameters),
+            tail: Vec::new(),
+            total_len: 0,
+        }
+    }
+
+    /// Appends `bytes` to the input, absorbing every chunk they complete.
+    pub fn update(&mut self, bytes: &[u8]) {
+        let chunk_len = (F::Params::CAPACITY / 8) as usize;
+        self.total_len += bytes.len() as u64;
+
+        // Complete the buffered chunk first.
+        let (head, bytes) = bytes.split_at((chunk_len - self.tail.len()).min(bytes.len()));
+        self.tail.extend_from_slice(head);
+        if self.tail.len() < chunk_len {
+            return;
+        }
+        self.sponge
+            .absorb_elements(&[F::from_le_bytes_mod_order(&self.tail)]);
+        self.tail.clear();
+
+        let mut chunks = bytes.chunks_exact(chunk_len);
+        let elements: Vec<F> = chunks.by_ref().map(F::from_le_bytes_mod_order).collect();
+        self.sponge.absorb_elements(&elements);
+        self.tail.extend_from_slice(chunks.remainder());
+    }
+
+    /// Returns the digest of the input.
+    pub fn finalize(mut self) -> F {
+        if !self.tail.is_empty() {
+            self.sponge
+                .absorb_elements(&[F::from_le_bytes_mod_order(&self.tail)]);
+        }
+        self.sponge.absorb_elements(&[F::from(self.total_len)]);
+        self.sponge.squeeze_native_field_elements(1)[0]
+    }
+}
+
+#[derive(Clone)]
+/// A message authentication code built on `PoseidonSponge`.
+///
+/// The tag of a message is the first native field element squeezed by a fresh sponge after
+/// absorbing the key and then the message. The key is a single secret field element, which
+/// makes the construction a keyed sponge with the key as a prefix.
+pub struct PoseidonMac<F: PrimeField> {
+    parameters: PoseidonParameters<F>,
+    key: F,
+}
+
+impl<F: PrimeField> PoseidonMac<F> {
+    /// Creates a MAC with the secret `key`.
+    pub fn new(parameters: &PoseidonParameters<F>, key: F) -> Self {
+        Self {
+            parameters: parameters.clone(),
+            key,
+        }
+    }
+
+    /// Returns the tag of `message`.
+    pub fn mac(&self, message: &impl Absorb) -> F {
+        let mut sponge = PoseidonSponge::new(&self.parameters);
+        sponge.absorb_elements(&[self.key]);
+        sponge.absorb(message);
+        sponge.squeeze_native_field_elements(1)[0]
+    }
+
+    /// Returns whether `tag` is the tag of `message`.
+    pub fn verify(&self, message: &impl Absorb, tag: &F) -> bool {
+        self.mac(message) == *tag
+    }
+}
+
+#[derive(Clone)]
+/// A pseudorandom function built on `PoseidonSponge`, with outputs of any length.
+///
+/// A fresh sponge first adds one to its first capacity element, which keeps outputs apart from
+/// the tags of [`PoseidonMac`] and from plain hashes under the same parameters. It then absorbs
+/// the key, a single secret field element, followed by the input, and squeezes the output. As
+/// the output is a stream of squeezed elements, the output of length `n` is a prefix of every
+/// longer output for the same key and input.
+pub struct PoseidonPrf<F: PrimeField> {
+    parameters: PoseidonParameters<F>,
+    key: F,
+}
+
+impl<F: PrimeField> PoseidonPrf<F> {
+    /// Creates a PRF with the secret `key`.
+    ///
+    /// Panics if `parameters` has no capacity to tag.
+    pub fn new(parameters: &PoseidonParameters<F>, key: F) -> Self {
+        assert!(
+            parameters.capacity > 0,
+            "the PRF is domain-separated through the capacity"
+        );
+        Self {
+            parameters: parameters.clone(),
+            key,
+        }
+    }
+
+    /// Returns the first `output_len` output elements for `input`.
+    pub fn prf(&self, input: &impl Absorb, output_len: usize) -> Vec<F> {
+        let mut sponge = PoseidonSponge::new(&self.parameters);
+        sponge.state[0] += F::one();
+        sponge.absorb_elements(&[self.key]);
+        sponge.absorb(input);
+        sponge.squeeze_native_field_elements(output_len)
+    }
+}
+
+/// Hashes `inputs` into a single element.
+///
+/// The output equals the first element squeezed by a fresh sponge after absorbing `inputs` as a
+/// slice of native field elements, but only the state and the buffer of the linear layer are
+/// allocated, instead of a full sponge with a copy of `parameters`.
+pub fn poseidon_hash<F: PrimeField>(parameters: &PoseidonParameters<F>, inputs: &[F]) -> F {
+    if let Err(error) = parameters.check_dimensions() {
+        panic!("invalid Poseidon parameters: {}", error);
+    }
+
+    let mut state = parameters.initial_state();
+    let mut scratch = Vec::with_capacity(state.len());
+    for (i, block) in inputs.chunks(parameters.rate).enumerate() {
+        // Like the sponge, permute lazily, once the next block is known to be needed.
+        if i > 0 {
+            PoseidonSponge::permute_state(parameters, &mut state, &mut scratch);
+        }
+        for (state_elem, input) in state[parameters.capacity..].iter_mut().zip(block) {
+            *state_elem += input;
+        }
+    }
+    PoseidonSponge::permute_state(parameters, &mut state, &mut scratch);
+
+    state[parameters.capacity]
+}
+
+/// Hashes each of `inputs` into a single element.
+///
+/// The output for each input equals the first element squeezed by a fresh sponge after
+/// absorbing the `RATE` elements of that input. The states of the whole batch are stored column
+/// by column, with all the first state elements together and so on, so that every step of the
+/// permutation runs an inner loop over the batch. `RATE` must equal `parameters.rate`.
+pub fn hash_batch<F: PrimeField, const RATE: usize>(
+    parameters: &PoseidonParameters<F>,
+    inputs: &[[F; RATE]],
+) -> Vec<F> {
+    assert_eq!(
+        RATE, parameters.rate,
+        "the inputs must have as many elements as the rate"
+    );
+
+    let width = parameters.rate + parameters.capacity;
+    let mut columns: Vec<_> = parameters
+        .initial_state()
+        .into_iter()
+        .map(|elem| vec![elem; inputs.len()])
+        .collect();
+    for (b, input) in inputs.iter().enumerate() {
+        for (j, elem) in input.iter().enumerate() {
+            columns[parameters.capacity + j][b] = *elem;
+        }
+    }
+
+    let full_rounds_over_2 = parameters.full_rounds / 2;
+    let partial_rounds_end = full_rounds_over_2 + parameters.partial_rounds;
+    let mut scratch = vec![vec![F::zero(); inputs.len()]; width];
+    for round in 0..(parameters.full_rounds + parameters.partial_rounds) {
+        for (column, ark) in columns.iter_mut().zip(&parameters.ark[round]) {
+            for elem in column.iter_mut() {
+                elem.add_assign(ark);
+            }
+        }
+
+        let is_full_round = round < full_rounds_over_2 || round >= partial_rounds_end;
+        let s_boxed = if is_full_round { width } else { 1 };
+        for column in columns.iter_mut().take(s_boxed) {
+            for elem in column.iter_mut() {
+                *elem = elem.pow([parameters.alpha]);
+            }
+        }
+
+        for (new_column, mds_row) in scratch.iter_mut().zip(&parameters.mds) {
+            for elem in new_column.iter_mut() {
+                *elem = F::zero();
+            }
+            for (column, mds_elem) in columns.iter().zip(mds_row) {
+                for (new_elem, elem) in new_column.iter_mut().zip(column) {
+                    new_elem.add_assign(&elem.mul(mds_elem));
+                }
+            }
+        }
+        ark_std::mem::swap(&mut columns, &mut scratch);
+    }
+
+    ark_std::mem::take(&mut columns[parameters.capacity])
+}
+
+/// Overwrites the state with zeros, keeping its length. The parameters and the mode are not
+/// secret and are left untouched.
+///
+/// The state is also zeroized when the sponge is dropped, as it is a [`StateVec`].
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> zeroize::Zeroize for PoseidonSponge<F> {
+    fn zeroize(&mut self) {
+        self.state.zeroize()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: PrimeField> zeroize::ZeroizeOnDrop for PoseidonSponge<F> {}
+
 #[cfg(test)]
 mod test {
     use crate::poseidon::{
@@ -1,7 +1,16 @@
-use crate::poseidon::{PoseidonParameters, PoseidonSponge};
-use crate::{absorb, collect_sponge_bytes, collect_sponge_field_elements};
-use crate::{Absorb, AbsorbWithLength, CryptographicSponge, FieldBasedCryptographicSponge};
-use ark_ff::{One, PrimeField, UniformRand};
+use crate::poseidon::{
+    find_poseidon_ark_and_mds, hash_batch, poseidon_hash, AbsorbMode, ParameterError,
+    PoseidonHasher, PoseidonParameters, PoseidonSponge,
+};
+use crate::{
+    absorb, collect_sponge_bytes, collect_sponge_field_elements,
+    squeeze_field_elements_with_sizes_default_impl, FieldElementSize,
+};
+use crate::{
+    Absorb, AbsorbWithLength, CryptographicSponge, DuplexSpongeMode, FieldBasedCryptographicSponge,
+};
+use ark_ff::{field_new, BigInteger, FpParameters, One, PrimeField, UniformRand, Zero};
+use ark_std::io::{self, Cursor, Read};
 use ark_std::test_rng;
 use ark_test_curves::bls12_381::Fr;
 fn assert_different_encodings<F: PrimeField, A: Absorb>(a: &A, b: &A) {
@@ -115,7 +124,753 @@ fn test_macros() {
     assert_eq!(actual, expected);
 }
 
+#[test]
+fn test_new_checked() {
+    let params = poseidon_parameters_for_test::<Fr>();
+    let new_checked = |alpha, mds, ark| {
+        PoseidonParameters::new_checked(
+            params.full_rounds,
+            params.partial_rounds,
+            alpha,
+            mds,
+            ark,
+            params.rate,
+            params.capacity,
+        )
+    };
+
+    let checked = new_checked(params.alpha, params.mds.clone(), params.ark.clone()).unwrap();
+    assert_eq!(checked.mds, params.mds);
+    assert_eq!(checked.ark, params.ark);
+
+    let mut mds = params.mds.clone();
+    mds.pop();
+    assert_eq!(
+        new_checked(params.alpha, mds, params.ark.clone()).unwrap_err(),
+        ParameterError::MdsRowCount {
+            expected: 3,
+            found: 2
+        }
+    );
+
+    let mut mds = params.mds.clone();
+    mds[1].push(Fr::one());
+    assert_eq!(
+        new_checked(params.alpha, mds, params.ark.clone()).unwrap_err(),
+        ParameterError::MdsRowLength {
+            row: 1,
+            expected: 3,
+            found: 4
+        }
+    );
+
+    let mut ark = params.ark.clone();
+    ark.pop();
+    assert_eq!(
+        new_checked(params.alpha, params.mds.clone(), ark).unwrap_err(),
+        ParameterError::ArkRowCount {
+            expected: 37,
+            found: 36
+        }
+    );
+
+    let mut ark = params.ark.clone();
+    ark[5].pop();
+    assert_eq!(
+        new_checked(params.alpha, params.mds.clone(), ark).unwrap_err(),
+        ParameterError::ArkRowLength {
+            row: 5,
+            expected: 3,
+            found: 2
+        }
+    );
+
+    // The last row is the sum of the first two.
+    let mut mds = params.mds.clone();
+    mds[2] = (0..3).map(|j| mds[0][j] + mds[1][j]).collect();
+    assert_eq!(
+        new_checked(params.alpha, mds, params.ark.clone()).unwrap_err(),
+        ParameterError::SingularMds
+    );
+    let mut mds = params.mds.clone();
+    mds[0] = vec![Fr::zero(); 3];
+    assert_eq!(
+        new_checked(params.alpha, mds, params.ark.clone()).unwrap_err(),
+        ParameterError::SingularMds
+    );
+
+    // 3 divides r - 1 for the BLS12-381 scalar field, whereas 5 does not.
+    assert_eq!(
+        new_checked(3, params.mds.clone(), params.ark.clone()).unwrap_err(),
+        ParameterError::AlphaNotCoprime { alpha: 3 }
+    );
+    assert!(new_checked(5, params.mds.clone(), params.ark.clone()).is_ok());
+    assert_eq!(
+        new_checked(0, params.mds.clone(), params.ark.clone()).unwrap_err(),
+        ParameterError::AlphaNotCoprime { alpha: 0 }
+    );
+    // 1 is coprime to everything, but `x^1` is linear.
+    let error = new_checked(1, params.mds.clone(), params.ark.clone()).unwrap_err();
+    assert_eq!(error, ParameterError::AlphaTooSmall { alpha: 1 });
+    assert_eq!(
+        error.to_string(),
+        "alpha = 1 is less than 3, so x^alpha is not a secure S-box"
+    );
+}
+
 /// Generate default parameters (bls381-fr-only) for alpha = 17, state-size = 8
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "the state must have `rate + capacity` elements")]
+fn test_mismatched_state_length() {
+    use crate::SpongeExt;
+
+    let sponge_param = poseidon_parameters_for_test();
+    let mut state = PoseidonSponge::<Fr>::new(&sponge_param).into_state();
+    state.state.pop();
+
+    let mut sponge = PoseidonSponge::from_state(state, &sponge_param);
+    sponge.absorb(&Fr::one());
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_zeroize() {
+    use ark_ff::Zero;
+    use zeroize::Zeroize;
+
+    let mut rng = test_rng();
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::rand(&mut rng));
+    let _ = sponge.squeeze_native_field_elements(1);
+    assert!(sponge.state.iter().any(|elem| !elem.is_zero()));
+
+    sponge.zeroize();
+    assert_eq!(sponge.state, vec![Fr::zero(); 3]);
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_zeroize_on_drop() {
+    use ark_ff::Zero;
+    use zeroize::Zeroize;
+
+    fn assert_zeroize_on_drop<T: zeroize::ZeroizeOnDrop>(_: &T) {}
+
+    let mut rng = test_rng();
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::rand(&mut rng));
+    let _ = sponge.squeeze_native_field_elements(1);
+    assert_zeroize_on_drop(&sponge);
+    assert_zeroize_on_drop(&sponge.state);
+
+    // The sponge itself has no `Drop` impl, so its fields can still be moved out of it. The
+    // state clears itself like the sponge does, which is what its `Drop` runs.
+    let PoseidonSponge {
+        mut state, mode, ..
+    } = sponge;
+    assert!(state.iter().any(|elem| !elem.is_zero()));
+    assert!(matches!(mode, DuplexSpongeMode::Squeezing { .. }));
+    state.zeroize();
+    assert_eq!(state, vec![Fr::zero(); 3]);
+}
+
+#[test]
+fn test_empty_squeeze() {
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::one());
+    let mut expected = sponge.clone();
+
+    assert!(sponge.squeeze_native_field_elements(0).is_empty());
+    assert!(sponge.squeeze_field_elements::<Fr>(0).is_empty());
+    assert!(sponge.squeeze_bytes(0).is_empty());
+    assert!(sponge.squeeze_bits(0).is_empty());
+    assert_eq!(sponge.state, expected.state);
+    assert!(matches!(
+        sponge.mode,
+        DuplexSpongeMode::Absorbing {
+            next_absorb_index: 1
+        }
+    ));
+
+    assert_eq!(
+        sponge.squeeze_native_field_elements(3),
+        expected.squeeze_native_field_elements(3)
+    );
+}
+
+#[test]
+fn test_hasher() {
+    use ark_std::io::Cursor;
+
+    let sponge_param = poseidon_parameters_for_test();
+    // Two full chunks of 31 bytes and a partial one.
+    let input: Vec<u8> = (0..70u8).collect();
+    let chunk_len = (<Fr as PrimeField>::Params::CAPACITY / 8) as usize;
+    assert_eq!(chunk_len, 31);
+
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb_reader(Cursor::new(&input)).unwrap();
+    let expected = sponge.squeeze_native_field_elements(1)[0];
+
+    let mut whole = PoseidonHasher::<Fr>::new(&sponge_param);
+    whole.update(&input);
+    assert_eq!(whole.tail.len(), 70 - 2 * chunk_len);
+    assert_eq!(whole.finalize(), expected);
+
+    for split in [0, 1, 30, 31, 32, 62, 69, 70] {
+        let mut hasher = PoseidonHasher::<Fr>::new(&sponge_param);
+        hasher.update(&input[..split]);
+        hasher.update(&input[split..]);
+        assert_eq!(hasher.finalize(), expected);
+    }
+
+    let mut trickled = PoseidonHasher::<Fr>::new(&sponge_param);
+    for byte in &input {
+        trickled.update(&[*byte]);
+        assert!(trickled.tail.len() < chunk_len);
+    }
+    assert_eq!(trickled.finalize(), expected);
+
+    // Absorbing the bytes as a single slice prefixes the length instead.
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&input.as_slice());
+    assert_ne!(sponge.squeeze_native_field_elements(1)[0], expected);
+}
+
+#[test]
+fn test_absorb_message_known_answer() {
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    let message = b"The quick brown fox jumps over the lazy dog";
+    sponge.absorb_message(message);
+    let digest = sponge.squeeze_native_field_elements(1)[0];
+    assert_eq!(
+        digest,
+        field_new!(
+            Fr,
+            "50305547211724381069820843476361931952737931057174429000027211042055594335158"
+        )
+    );
+
+    // The 43 bytes are absorbed as their length, a full block of 31 bytes and a block of 12.
+    let mut expected = PoseidonSponge::<Fr>::new(&sponge_param);
+    expected.absorb(&vec![
+        Fr::from(43u64),
+        Fr::from_le_bytes_mod_order(&message[..31]),
+        Fr::from_le_bytes_mod_order(&message[31..]),
+    ]);
+    assert_eq!(expected.squeeze_native_field_elements(1)[0], digest);
+}
+
+#[test]
+fn test_finalize_absorb() {
+    let sponge_param = poseidon_parameters_for_test::<Fr>();
+    let rate = sponge_param.rate;
+    assert_eq!(rate, 2);
+
+    // A textbook sponge hash: pad the message with `10*1` to a multiple of the rate, add each
+    // block to the rate part of the state and permute, then read the first output.
+    let reference_hash = |message: &[Fr]| {
+        let mut padded = message.to_vec();
+        padded.push(Fr::one());
+        while padded.len() % rate != rate - 1 {
+            padded.push(Fr::zero());
+        }
+        padded.push(Fr::one());
+
+        let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+        for block in padded.chunks(rate) {
+            for (i, elem) in block.iter().enumerate() {
+                sponge.state[sponge_param.capacity + i] += elem;
+            }
+            sponge.permute();
+        }
+        sponge.state[sponge_param.capacity]
+    };
+
+    // These lengths cover a padding within one block, spanning two blocks, and filling a new one.
+    for len in 0..5u64 {
+        let message: Vec<_> = (0..len).map(Fr::from).collect();
+        let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+        sponge.absorb(&message);
+        sponge.finalize_absorb();
+        assert_eq!(
+            sponge.squeeze_native_field_elements(1)[0],
+            reference_hash(&message)
+        );
+    }
+}
+
+#[test]
+fn test_absorb_reader() {
+    // Yields at most three bytes per read.
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(3).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    // Fails its first read with `Interrupted`, then reads like a `Cursor`.
+    struct Interrupting<'a>(bool, Cursor<&'a [u8]>);
+    impl Read for Interrupting<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !self.0 {
+                self.0 = true;
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            self.1.read(buf)
+        }
+    }
+
+    let sponge_param = poseidon_parameters_for_test();
+    let bytes: Vec<u8> = (0..100).collect();
+    for len in [0, 31, 62, 100] {
+        let bytes = &bytes[..len];
+        let mut expected = PoseidonSponge::<Fr>::new(&sponge_param);
+        let mut elements: Vec<Fr> = bytes.chunks(31).map(Fr::from_le_bytes_mod_order).collect();
+        elements.push(Fr::from(len as u64));
+        expected.absorb(&elements);
+
+        let mut whole = PoseidonSponge::<Fr>::new(&sponge_param);
+        whole.absorb_reader(Cursor::new(bytes)).unwrap();
+        let mut trickled = PoseidonSponge::<Fr>::new(&sponge_param);
+        trickled.absorb_reader(Trickle(bytes)).unwrap();
+        let mut interrupted = PoseidonSponge::<Fr>::new(&sponge_param);
+        interrupted
+            .absorb_reader(Interrupting(false, Cursor::new(bytes)))
+            .unwrap();
+
+        let mut output = Vec::new();
+        whole.squeeze_writer(&mut output, 40).unwrap();
+        assert_eq!(output, expected.squeeze_bytes(40));
+        assert_eq!(output, trickled.squeeze_bytes(40));
+        assert_eq!(output, interrupted.squeeze_bytes(40));
+    }
+}
+
+#[test]
+fn test_absorb_reader_partial_chunk() {
+    let sponge_param = poseidon_parameters_for_test();
+    // One full chunk of 31 bytes and a partial chunk of 14 bytes.
+    let bytes: Vec<u8> = (1..=45).collect();
+    let mut expected = PoseidonSponge::<Fr>::new(&sponge_param);
+    expected.absorb(&vec![
+        Fr::from_le_bytes_mod_order(&bytes[..31]),
+        Fr::from_le_bytes_mod_order(&bytes[31..]),
+        Fr::from(45u64),
+    ]);
+
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb_reader(Cursor::new(&bytes)).unwrap();
+    assert_eq!(
+        sponge.squeeze_native_field_elements(2),
+        expected.squeeze_native_field_elements(2)
+    );
+
+    // A partial chunk is not zero-padded into a full one: only the length tells them apart.
+    let mut padded = bytes.clone();
+    padded.resize(62, 0);
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb_reader(Cursor::new(&bytes)).unwrap();
+    let mut padded_sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    padded_sponge.absorb_reader(Cursor::new(&padded)).unwrap();
+    assert_ne!(
+        sponge.squeeze_native_field_elements(1),
+        padded_sponge.squeeze_native_field_elements(1)
+    );
+}
+
+#[test]
+fn test_squeeze_field_elements_uniform() {
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::from(1u64));
+
+    // `2^254` is about 55% of the modulus of `Fr`. Elements squeezed from `CAPACITY` bits are
+    // all below it, while uniform elements are above it with probability about 0.447.
+    let high = |elems: &[Fr]| {
+        let count = elems
+            .iter()
+            .filter(|e| e.into_repr().num_bits() > 254)
+            .count();
+        count as f64 / elems.len() as f64
+    };
+    let num_samples = 1000;
+    let bounded: Vec<Fr> = squeeze_field_elements_with_sizes_default_impl(
+        &mut sponge.clone(),
+        &vec![FieldElementSize::Full; num_samples],
+    );
+    assert_eq!(high(&bounded), 0.0);
+
+    // The standard deviation of the fraction is about 0.016.
+    let uniform: Vec<Fr> = sponge.squeeze_field_elements_uniform(num_samples);
+    assert!((high(&uniform) - 0.447).abs() < 0.06);
+    // About half of the elements are in the lower half of the field.
+    let half = <Fr as PrimeField>::Params::MODULUS_MINUS_ONE_DIV_TWO;
+    let low = uniform.iter().filter(|e| e.into_repr() <= half).count();
+    assert!((low as f64 / num_samples as f64 - 0.5).abs() < 0.06);
+}
+
+#[test]
+#[should_panic(
+    expected = "invalid Poseidon parameters: row 4 of the round keys has 4 entries, but the state has 3 elements"
+)]
+fn test_ragged_ark() {
+    let mut sponge_param = poseidon_parameters_for_test::<Fr>();
+    sponge_param.ark[4].push(Fr::one());
+    PoseidonSponge::new(&sponge_param);
+}
+
+#[test]
+fn test_squeeze_across_blocks() {
+    // With a rate of 2, squeezing 1 and then 2 elements starts the second squeeze in the
+    // middle of a block, which must not reuse the elements of the first block.
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::from(1u64));
+    let expected = sponge.clone().squeeze_native_field_elements(3);
+
+    let mut squeezed = sponge.squeeze_native_field_elements(1);
+    squeezed.append(&mut sponge.squeeze_native_field_elements(2));
+    assert_eq!(squeezed, expected);
+}
+
+#[test]
+fn test_squeeze_rate() {
+    let mut sponge_param = poseidon_parameters_for_test::<Fr>();
+    assert_eq!(sponge_param.rate, 2);
+    sponge_param.squeeze_rate = Some(1);
+    let mut sponge = PoseidonSponge::new(&sponge_param);
+    sponge.absorb(&Fr::from(1u64));
+
+    // Every element is squeezed from the first rate position of a new permutation.
+    let mut state = sponge.state.to_vec();
+    let mut scratch = Vec::new();
+    let mut expected = Vec::new();
+    for _ in 0..3 {
+        PoseidonSponge::permute_state(&sponge_param, &mut state, &mut scratch);
+        expected.push(state[sponge_param.capacity]);
+    }
+    assert_eq!(sponge.clone().squeeze_native_field_elements(3), expected);
+
+    // A squeeze that ends on the squeeze rate leaves the permutation to the next squeeze.
+    let mut squeezed = sponge.squeeze_native_field_elements(1);
+    assert!(matches!(
+        sponge.mode,
+        DuplexSpongeMode::Squeezing {
+            next_squeeze_index: 1
+        }
+    ));
+    squeezed.append(&mut sponge.squeeze_native_field_elements(2));
+    assert_eq!(squeezed, expected);
+
+    for squeeze_rate in [0, 3] {
+        sponge_param.squeeze_rate = Some(squeeze_rate);
+        assert_eq!(
+            sponge_param.check_dimensions(),
+            Err(ParameterError::SqueezeRate {
+                squeeze_rate,
+                rate: 2
+            })
+        );
+    }
+}
+
+#[test]
+fn test_initial_state_iv() {
+    let sponge_param = poseidon_parameters_for_test::<Fr>();
+    let with_iv = |iv: Option<Vec<Fr>>| {
+        let mut sponge_param = sponge_param.clone();
+        sponge_param.initial_state_iv = iv;
+        sponge_param
+    };
+    let squeeze = |sponge_param: &PoseidonParameters<Fr>| {
+        let mut sponge = PoseidonSponge::new(sponge_param);
+        sponge.absorb(&Fr::from(1u64));
+        sponge.squeeze_native_field_elements(2)
+    };
+
+    let iv_1 = with_iv(Some(vec![Fr::from(1u64)]));
+    let iv_2 = with_iv(Some(vec![Fr::from(2u64)]));
+    assert_eq!(
+        PoseidonSponge::new(&iv_1).state,
+        vec![Fr::from(1u64), Fr::zero(), Fr::zero()]
+    );
+    assert_ne!(squeeze(&iv_1), squeeze(&iv_2));
+    assert_ne!(squeeze(&iv_1), squeeze(&sponge_param));
+    // A zero IV is the same as none.
+    assert_eq!(
+        squeeze(&with_iv(Some(vec![Fr::zero()]))),
+        squeeze(&sponge_param)
+    );
+
+    // The one-shot hashes start from the IV too, as a fresh sponge does.
+    let inputs = [Fr::from(3u64), Fr::from(4u64)];
+    let mut sponge = PoseidonSponge::new(&iv_1);
+    sponge.absorb(&inputs.to_vec());
+    let expected = sponge.squeeze_native_field_elements(1)[0];
+    assert_eq!(poseidon_hash(&iv_1, &inputs), expected);
+    assert_eq!(hash_batch(&iv_1, &[inputs]), vec![expected]);
+
+    assert_eq!(
+        with_iv(Some(Vec::new())).check_dimensions(),
+        Err(ParameterError::IvLength {
+            expected: 1,
+            found: 0
+        })
+    );
+}
+
+#[test]
+fn test_absorb_mode() {
+    let sponge_param = poseidon_parameters_for_test::<Fr>();
+    assert_eq!(sponge_param.effective_absorb_mode(), AbsorbMode::Add);
+    let mut overwrite_param = sponge_param.clone();
+    overwrite_param.absorb_mode = Some(AbsorbMode::Overwrite);
+    let absorb = |sponge_param: &PoseidonParameters<Fr>, inputs: &[u64]| {
+        let mut sponge = PoseidonSponge::new(sponge_param);
+        sponge.absorb(&inputs.iter().map(|i| Fr::from(*i)).collect::<Vec<_>>());
+        sponge.state.to_vec()
+    };
+
+    // A fresh rate is zero, so both modes absorb a first block alike.
+    assert_eq!(
+        absorb(&sponge_param, &[1, 2]),
+        absorb(&overwrite_param, &[1, 2])
+    );
+
+    // The third element lands on the permuted rate, where it is either added or written as it
+    // is, and the rest of the state is the same.
+    let capacity = sponge_param.capacity;
+    let added = absorb(&sponge_param, &[1, 2, 3]);
+    let overwritten = absorb(&overwrite_param, &[1, 2, 3]);
+    assert_ne!(added, overwritten);
+    assert_eq!(overwritten[capacity], Fr::from(3u64));
+    assert_eq!(added[..capacity], overwritten[..capacity]);
+    assert_eq!(added[capacity + 1..], overwritten[capacity + 1..]);
+}
+
+#[test]
+fn test_hash_batch() {
+    let mut rng = test_rng();
+    let sponge_params = poseidon_parameters_for_test::<Fr>();
+    let inputs: Vec<[Fr; 2]> = (0..5)
+        .map(|_| [Fr::rand(&mut rng), Fr::rand(&mut rng)])
+        .collect();
+
+    let expected: Vec<_> = inputs
+        .iter()
+        .map(|input| {
+            let mut sponge = PoseidonSponge::new(&sponge_params);
+            sponge.absorb(&input.to_vec());
+            sponge.squeeze_native_field_elements(1)[0]
+        })
+        .collect();
+    assert_eq!(hash_batch(&sponge_params, &inputs), expected);
+    assert!(hash_batch::<Fr, 2>(&sponge_params, &[]).is_empty());
+}
+
+#[test]
+fn test_squeeze_truncated_up_to_capacity() {
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::from(1u64));
+
+    let capacity = <Fr as PrimeField>::Params::CAPACITY as usize;
+    let truncated =
+        sponge.squeeze_field_elements_with_sizes::<Fr>(&[FieldElementSize::Truncated(capacity)]);
+    assert!(truncated[0].into_repr().num_bits() as usize <= capacity);
+}
+
+#[test]
+#[should_panic(expected = "num_bits is greater than the capacity of the field.")]
+fn test_squeeze_truncated_above_capacity() {
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::from(1u64));
+
+    // `MODULUS_BITS` bits can encode integers above the modulus, which would then be reduced.
+    let modulus_bits = <Fr as PrimeField>::Params::MODULUS_BITS as usize;
+    sponge.squeeze_field_elements_with_sizes::<Fr>(&[FieldElementSize::Truncated(modulus_bits)]);
+}
+
+#[test]
+fn test_squeeze_truncated_native() {
+    let mut rng = test_rng();
+    let sponge_param = poseidon_parameters_for_test();
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::rand(&mut rng));
+
+    for elem in sponge
+        .clone()
+        .squeeze_field_elements_with_sizes::<Fr>(&[FieldElementSize::Truncated(64); 8])
+    {
+        assert!(elem.into_repr().num_bits() <= 64);
+    }
+
+    // A mixed call takes consecutive windows of the squeezed bits, in order.
+    let sizes = [
+        FieldElementSize::Truncated(64),
+        FieldElementSize::Full,
+        FieldElementSize::Truncated(10),
+    ];
+    let widths = [64, <Fr as PrimeField>::Params::CAPACITY as usize, 10];
+    let bits = sponge.clone().squeeze_bits(widths.iter().sum());
+    let mut window = bits.as_slice();
+    let expected: Vec<_> = widths
+        .iter()
+        .map(|&width| {
+            let (elem_bits, rest) = window.split_at(width);
+            window = rest;
+            Fr::from_repr(<Fr as PrimeField>::BigInt::from_bits_le(elem_bits)).unwrap()
+        })
+        .collect();
+    assert_eq!(
+        sponge.squeeze_field_elements_with_sizes::<Fr>(&sizes),
+        expected
+    );
+}
+
+#[test]
+fn test_poseidon_hash() {
+    let mut rng = test_rng();
+    let sponge_params = poseidon_parameters_for_test::<Fr>();
+    let inputs: Vec<_> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+
+    // Cover empty inputs, partial blocks and full blocks of the rate 2.
+    for len in 0..=inputs.len() {
+        let mut sponge = PoseidonSponge::new(&sponge_params);
+        sponge.absorb(&&inputs[..len]);
+        assert_eq!(
+            poseidon_hash(&sponge_params, &inputs[..len]),
+            sponge.squeeze_native_field_elements(1)[0]
+        );
+    }
+}
+
+#[cfg(all(feature = "poseidon-trace", feature = "std"))]
+#[test]
+fn test_permutation_trace() {
+    use ark_std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // Collects the `state` field of every event.
+    struct StateCollector(Arc<Mutex<Vec<String>>>);
+
+    struct StateVisitor<'a>(&'a mut Vec<String>);
+
+    impl Visit for StateVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "state" {
+                self.0.push(format!("{:?}", value));
+            }
+        }
+    }
+
+    impl Subscriber for StateCollector {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut StateVisitor(&mut self.0.lock().unwrap()));
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let states = Arc::new(Mutex::new(Vec::new()));
+    let sponge_params = poseidon_parameters_for_test::<Fr>();
+    let mut sponge = PoseidonSponge::new(&sponge_params);
+    sponge.absorb(&Fr::one());
+    tracing::subscriber::with_default(StateCollector(states.clone()), || {
+        sponge.squeeze_native_field_elements(1)
+    });
+
+    let zero = format!("0x{}", "0".repeat(64));
+    let one = format!("0x{}1", "0".repeat(63));
+    let output = sponge
+        .state
+        .iter()
+        .map(|elem| {
+            let digits: String = elem
+                .into_repr()
+                .to_bytes_be()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            format!("0x{}", digits)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    assert_eq!(
+        *states.lock().unwrap(),
+        vec![
+            format!("[{}, {}, {}]", zero, one, zero),
+            format!("[{}]", output)
+        ]
+    );
+}
+
+#[test]
+fn test_zero_rate_and_capacity() {
+    let new_checked = |rate, capacity| {
+        // The matrices are generated for a capacity of 1, so that they have the same size.
+        let (ark, mds) = find_poseidon_ark_and_mds::<Fr>(255, rate + capacity - 1, 8, 31, 0);
+        PoseidonParameters::new_checked(8, 31, 5, mds, ark, rate, capacity)
+    };
+
+    assert!(new_checked(2, 1).is_ok());
+    assert_eq!(new_checked(0, 3).unwrap_err(), ParameterError::ZeroRate);
+    assert_eq!(new_checked(3, 0).unwrap_err(), ParameterError::ZeroCapacity);
+}
+
+#[test]
+#[should_panic(expected = "invalid Poseidon parameters: the rate must be at least 1")]
+fn test_zero_rate_sponge() {
+    let mut sponge_params = poseidon_parameters_for_test::<Fr>();
+    sponge_params.capacity += sponge_params.rate;
+    sponge_params.rate = 0;
+    PoseidonSponge::new(&sponge_params);
+}
+
+#[test]
+fn test_absorb_internal_full_block() {
+    let mut rng = test_rng();
+    let sponge_params = poseidon_parameters_for_test::<Fr>();
+    let block = [Fr::rand(&mut rng), Fr::rand(&mut rng)];
+    let next = Fr::rand(&mut rng);
+
+    let mut sponge = PoseidonSponge::new(&sponge_params);
+    sponge.absorb(&block.to_vec());
+    let mut expected = sponge.clone();
+    expected.absorb(&next);
+
+    sponge.absorb_internal(sponge_params.rate, &[next]);
+    assert_eq!(sponge.state, expected.state);
+    assert!(matches!(
+        sponge.mode,
+        DuplexSpongeMode::Absorbing {
+            next_absorb_index: 1
+        }
+    ));
+}
+
 pub(crate) fn poseidon_parameters_for_test<F: PrimeField>() -> PoseidonParameters<F> {
     let alpha = 17;
     let mds = vec![
@@ -815,5 +1570,8 @@ pub(crate) fn poseidon_parameters_for_test<F: PrimeField>() -> PoseidonParameter
         mds,
         rate,
         capacity,
+        squeeze_rate: None,
+        initial_state_iv: None,
+        absorb_mode: None,
     }
 }
@@ -94,6 +94,9 @@ pub fn get_default_poseidon_parameters_internal<F: PrimeField, P: PoseidonDefaul
                 mds,
                 rate: param.rate,
                 capacity: 1,
+                squeeze_rate: None,
+                initial_state_iv: None,
+                absorb_mode: None,
             });
         }
     }
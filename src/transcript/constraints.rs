@@ -0,0 +1,106 @@
+use crate::constraints::{AbsorbGadget, CryptographicSpongeVar};
+use crate::CryptographicSponge;
+use ark_ff::PrimeField;
+use ark_r1cs_std::boolean::Boolean;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_relations::r1cs::SynthesisError;
+use ark_std::vec::Vec;
+
+/// The gadget of a [`Transcript`](crate::transcript::Transcript) whose constraint field is
+/// `CF`, for transcripts built on the sponge `S`.
+///
+/// Every `CryptographicSpongeVar` is a transcript, whose challenges match the ones of the
+/// native sponge `S` when the challenge field is `CF`.
+pub trait TranscriptVar<CF: PrimeField, S: CryptographicSponge> {
+    /// Absorbs a public message into the transcript.
+    ///
+    /// This is `CryptographicSpongeVar::absorb` under another name, so that calls are not
+    /// ambiguous when both traits are in scope.
+    fn append_message(&mut self, input: &impl AbsorbGadget<CF>) -> Result<(), SynthesisError>;
+
+    /// Squeezes a challenge field element.
+    fn squeeze_challenge(&mut self) -> Result<FpVar<CF>, SynthesisError>;
+
+    /// Squeezes a challenge of `num_bits` bits.
+    fn squeeze_challenge_bits(
+        &mut self,
+        num_bits: usize,
+    ) -> Result<Vec<Boolean<CF>>, SynthesisError>;
+}
+
+impl<CF, S, V> TranscriptVar<CF, S> for V
+where
+    CF: PrimeField,
+    S: CryptographicSponge,
+    V: CryptographicSpongeVar<CF, S>,
+{
+    fn append_message(&mut self, input: &impl AbsorbGadget<CF>) -> Result<(), SynthesisError> {
+        self.absorb(input)
+    }
+
+    fn squeeze_challenge(&mut self) -> Result<FpVar<CF>, SynthesisError> {
+        Ok(self.squeeze_field_elements(1)?.remove(0))
+    }
+
+    fn squeeze_challenge_bits(
+        &mut self,
+        num_bits: usize,
+    ) -> Result<Vec<Boolean<CF>>, SynthesisError> {
+        self.squeeze_bits(num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraints::CryptographicSpongeVar;
+    use crate::poseidon::constraints::PoseidonSpongeVar;
+    use crate::poseidon::tests::poseidon_parameters_for_test;
+    use crate::poseidon::PoseidonSponge;
+    use crate::transcript::constraints::TranscriptVar;
+    use crate::transcript::Transcript;
+    use crate::CryptographicSponge;
+    use ark_ff::UniformRand;
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_relations::*;
+    use ark_std::test_rng;
+    use ark_test_curves::bls12_381::Fr;
+
+    #[test]
+    fn transcript_challenges_match() {
+        let mut rng = test_rng();
+        let cs = ConstraintSystem::new_ref();
+        let sponge_params = poseidon_parameters_for_test();
+
+        let public_inputs: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let public_inputs_var: Vec<_> = public_inputs
+            .iter()
+            .map(|input| FpVar::new_input(ns!(cs, "input"), || Ok(*input)).unwrap())
+            .collect();
+
+        let mut transcript = PoseidonSponge::<Fr>::new(&sponge_params);
+        let mut transcript_var = PoseidonSpongeVar::<Fr>::new(cs.clone(), &sponge_params);
+
+        transcript.append_message(&public_inputs);
+        transcript_var.append_message(&public_inputs_var).unwrap();
+        assert_eq!(
+            transcript_var.squeeze_challenge().unwrap().value().unwrap(),
+            transcript.squeeze_challenge::<Fr>()
+        );
+
+        transcript.append_message(&public_inputs[0]);
+        transcript_var
+            .append_message(&public_inputs_var[0])
+            .unwrap();
+        assert_eq!(
+            transcript_var
+                .squeeze_challenge_bits(128)
+                .unwrap()
+                .value()
+                .unwrap(),
+            transcript.squeeze_challenge_bits(128)
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
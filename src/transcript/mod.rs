@@ -0,0 +1,41 @@
+use crate::{Absorb, CryptographicSponge};
+use ark_ff::PrimeField;
+use ark_std::vec::Vec;
+
+/// constraints for transcripts
+#[cfg(feature = "r1cs")]
+pub mod constraints;
+
+/// A Fiat-Shamir transcript: the prover and the verifier absorb the public messages of a
+/// protocol and squeeze the verifier's challenges from it.
+///
+/// Every `CryptographicSponge` is a transcript. Its challenges are the elements and bits the
+/// sponge squeezes, so they match the ones squeezed by [`constraints::TranscriptVar`] from the
+/// gadget of the same sponge.
+pub trait Transcript {
+    /// Absorbs a public message into the transcript.
+    ///
+    /// This is `CryptographicSponge::absorb` under another name, so that calls are not ambiguous
+    /// when both traits are in scope.
+    fn append_message(&mut self, input: &impl Absorb);
+
+    /// Squeezes a challenge field element.
+    fn squeeze_challenge<F: PrimeField>(&mut self) -> F;
+
+    /// Squeezes a challenge of `num_bits` bits.
+    fn squeeze_challenge_bits(&mut self, num_bits: usize) -> Vec<bool>;
+}
+
+impl<S: CryptographicSponge> Transcript for S {
+    fn append_message(&mut self, input: &impl Absorb) {
+        self.absorb(input)
+    }
+
+    fn squeeze_challenge<F: PrimeField>(&mut self) -> F {
+        self.squeeze_field_elements(1)[0]
+    }
+
+    fn squeeze_challenge_bits(&mut self, num_bits: usize) -> Vec<bool> {
+        self.squeeze_bits(num_bits)
+    }
+}